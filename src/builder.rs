@@ -0,0 +1,1032 @@
+//! Builder allowing a CORSMiddleware to be put together
+//! one option at a time.
+use hyper::{Method, StatusCode};
+use mime::Mime;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use config::OverrideCache;
+use disallowed::DisallowedResponse;
+use error::CorsError;
+use headers::{ActualAllowHeaders, AfterHeadersHook, AllowedHeaders, ExposeHeaders};
+use max_age::MaxAge;
+use methods::AllowedMethods;
+use origin::{
+    self, AsyncOriginResolver, ExtensionOrigin, OriginComparator, OriginMatcher, OriginPredicate,
+    OriginResolver, OriginSettings, OriginSource,
+};
+use path::PathPolicy;
+use report::{BlockLogLimiter, BlockReporter, HeaderDiffRecorder};
+#[cfg(feature = "suffix-allowlist")]
+use suffix::SuffixAllowlist;
+use CORSMiddleware;
+
+/// Chainable builder for a CORSMiddleware.
+///
+/// The builder starts out with the same values as
+/// CORSMiddleware::default(), so only the options that
+/// need changing have to be set.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham_cors_middleware;
+/// extern crate hyper;
+///
+/// use gotham_cors_middleware::CORSMiddleware;
+/// use hyper::Method;
+///
+/// fn main() {
+///     let middleware = CORSMiddleware::builder()
+///         .methods(vec![Method::Get, Method::Options])
+///         .origin(Some("http://www.example.com".to_string()))
+///         .max_age(1000)
+///         .allow_credentials(false)
+///         .allow_headers(vec!["Content-Type", "X-Api-Key"])
+///         .build();
+/// #   drop(middleware);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CORSMiddlewareBuilder {
+    middleware: CORSMiddleware,
+}
+
+impl CORSMiddlewareBuilder {
+    /// Creates a builder seeded with the default values.
+    pub fn new() -> CORSMiddlewareBuilder {
+        CORSMiddlewareBuilder {
+            middleware: CORSMiddleware::default(),
+        }
+    }
+
+    /// Sets the methods sent in Access-Control-Allow-Methods.
+    ///
+    /// OPTIONS does not need to be among them for preflights to
+    /// work, as those are answered whatever the methods. It is
+    /// only sent in Access-Control-Allow-Methods when listed.
+    pub fn methods(mut self, methods: Vec<Method>) -> CORSMiddlewareBuilder {
+        self.middleware.methods = AllowedMethods::new(methods);
+        self
+    }
+
+    /// Adds a method to those already allowed, such as the
+    /// defaults. A method which is already allowed is not
+    /// added again.
+    pub fn allow_method(self, method: Method) -> CORSMiddlewareBuilder {
+        self.allow_methods(vec![method])
+    }
+
+    /// Adds methods to those already allowed, after them in
+    /// Access-Control-Allow-Methods. Methods which are already
+    /// allowed are not added again.
+    pub fn allow_methods<I>(mut self, methods: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        let mut allowed = self.middleware.methods.to_vec();
+        allowed.extend(methods);
+
+        self.middleware.methods = AllowedMethods::new(allowed);
+        self
+    }
+
+    /// Sets the allowed methods from a set. They are sent in
+    /// Access-Control-Allow-Methods ordered by name, so the
+    /// header is the same from one run to the next.
+    pub fn method_set(mut self, methods: HashSet<Method>) -> CORSMiddlewareBuilder {
+        self.middleware.methods = AllowedMethods::from_set(methods);
+        self
+    }
+
+    /// Sets the allowed origin. None reflects the origin
+    /// of the request back to the client.
+    pub fn origin(mut self, origin: Option<String>) -> CORSMiddlewareBuilder {
+        self.middleware.origin = origin;
+        self
+    }
+
+    /// Sets the list of origins which are reflected back to
+    /// the client. Requests from any other origin do not
+    /// receive an Access-Control-Allow-Origin header.
+    ///
+    /// Only consulted when no fixed origin has been set.
+    ///
+    /// A pattern such as "https://*.example.com" allows any
+    /// subdomain of example.com with that scheme and port.
+    ///
+    /// Returns an error naming the first pattern which is
+    /// not a valid origin.
+    pub fn allowed_origins<I, S>(mut self, patterns: I) -> Result<CORSMiddlewareBuilder, CorsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.middleware.allowed_origins = origin::compile(patterns)?;
+        Ok(self)
+    }
+
+    /// Same as allowed_origins(), but taking matchers which have
+    /// already been parsed, and which may carry their own
+    /// OriginSettings. When several matchers match an origin,
+    /// the settings of the first one listed apply.
+    pub fn allowed_matchers(mut self, matchers: Vec<OriginMatcher>) -> CORSMiddlewareBuilder {
+        self.middleware.allowed_origins = matchers;
+        self
+    }
+
+    /// Same as allowed_origins(), but pairing each pattern with
+    /// whether Access-Control-Allow-Credentials is sent to the
+    /// origins it matches, in place of the allow_credentials()
+    /// setting. Trusted front ends can then send cookies while
+    /// third party embeds only read public data.
+    ///
+    /// Returns an error naming the first pattern which is
+    /// not a valid origin.
+    pub fn allowed_origins_with_credentials<I, S>(
+        mut self,
+        origins: I,
+    ) -> Result<CORSMiddlewareBuilder, CorsError>
+    where
+        I: IntoIterator<Item = (S, bool)>,
+        S: AsRef<str>,
+    {
+        let mut matchers = Vec::new();
+
+        for (pattern, credentials) in origins {
+            let matcher: OriginMatcher = pattern.as_ref().parse()?;
+
+            matchers.push(matcher.with_settings(OriginSettings {
+                credentials: Some(credentials),
+                ..OriginSettings::default()
+            }));
+        }
+
+        self.middleware.allowed_origins = matchers;
+        Ok(self)
+    }
+
+    /// Same as allowed_origins(), but the patterns are split
+    /// across the given number of threads while being parsed.
+    ///
+    /// This only pays off for allowlists running into the tens
+    /// of thousands of entries, where building the middleware
+    /// would otherwise noticeably delay start up. For smaller
+    /// lists the cost of spawning threads outweighs the gain.
+    pub fn allowed_origins_parallel(
+        mut self,
+        patterns: Vec<String>,
+        threads: usize,
+    ) -> Result<CORSMiddlewareBuilder, CorsError> {
+        self.middleware.allowed_origins = origin::compile_parallel(patterns, threads)?;
+        Ok(self)
+    }
+
+    /// Sets domain suffixes whose origins are reflected back to
+    /// the client alongside the allowed origins. A suffix such as
+    /// "example.com" allows the domain and all its subdomains,
+    /// on any port.
+    ///
+    /// The suffixes are compiled into a single automaton, which
+    /// keeps lookups fast for lists of tens of thousands.
+    #[cfg(feature = "suffix-allowlist")]
+    pub fn allowed_suffixes<I, S>(mut self, suffixes: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.allowed_suffixes = Some(SuffixAllowlist::new(suffixes));
+        self
+    }
+
+    /// Sets whether an empty allowlist allows no origins at all,
+    /// rather than reflecting any. This is on by default when the
+    /// crate is built with the "production" feature.
+    pub fn strict_allowlist(mut self, strict: bool) -> CORSMiddlewareBuilder {
+        self.middleware.strict_allowlist = strict;
+        self
+    }
+
+    /// Sets origins which are never allowed, even when they are
+    /// also the fixed origin or on the allowlist.
+    ///
+    /// Returns an error naming the first pattern which is
+    /// not a valid origin.
+    pub fn denied_origins<I, S>(mut self, patterns: I) -> Result<CORSMiddlewareBuilder, CorsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.middleware.denied_origins = origin::compile(patterns)?;
+        Ok(self)
+    }
+
+    /// Sets request headers which are never allowed, even when
+    /// they are configured, listed by a path policy or mirrored.
+    pub fn denied_headers<I, S>(mut self, headers: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.denied_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets a comparator which is run against each entry of the
+    /// allowlist, allowing origins it accepts in addition to those
+    /// matched by the built-in comparison.
+    pub fn origin_comparator(mut self, comparator: OriginComparator) -> CORSMiddlewareBuilder {
+        self.middleware.origin_comparator = Some(comparator);
+        self
+    }
+
+    /// Sets a predicate which is run against the origin of each
+    /// request, allowing origins it accepts in addition to those
+    /// in the allowlist. Origins it refuses are not allowed unless
+    /// the allowlist has them.
+    pub fn origin_predicate(mut self, predicate: OriginPredicate) -> CORSMiddlewareBuilder {
+        self.middleware.origin_predicate = Some(predicate);
+        self
+    }
+
+    /// Adds browser extensions to those which are reflected back
+    /// to the client, alongside the allowed origins. The scheme of
+    /// each extension is allowed along with the allowed schemes.
+    ///
+    /// Extensions are kept apart from the allowed origins and
+    /// schemes, so this can be called before or after
+    /// allowed_origins() and allowed_schemes().
+    pub fn allowed_extensions<I>(mut self, extensions: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = ExtensionOrigin>,
+    {
+        self.middleware.allowed_extensions.extend(extensions);
+        self
+    }
+
+    /// Sets whether a reflected origin is sent back as the exact
+    /// bytes of the request's Origin header, rather than the
+    /// origin as parsed, so the two always match byte for byte.
+    ///
+    /// The decision whether to allow the origin is unaffected.
+    pub fn reflect_raw_origin(mut self, raw: bool) -> CORSMiddlewareBuilder {
+        self.middleware.reflect_raw_origin = raw;
+        self
+    }
+
+    /// Sets a resolver which works out the allowed origins from
+    /// the State of each request, for example from a tenant put
+    /// there by an earlier middleware. Matching origins are
+    /// reflected back to the client.
+    ///
+    /// When set, the resolver takes the place of both the fixed
+    /// origin and the allowlist.
+    pub fn origin_resolver(mut self, resolver: OriginResolver) -> CORSMiddlewareBuilder {
+        self.middleware.origin_resolver = Some(resolver);
+        self
+    }
+
+    /// Sets a resolver which looks up the allowed origins for each
+    /// request asynchronously, before the rest of the chain runs.
+    /// Matching origins are reflected back to the client.
+    ///
+    /// The lookup runs once per request, with the result kept in
+    /// the State as ResolvedOrigins. A synchronous origin_resolver()
+    /// takes precedence when both are set.
+    pub fn async_origin_resolver(mut self, resolver: AsyncOriginResolver) -> CORSMiddlewareBuilder {
+        self.middleware.async_origin_resolver = Some(resolver);
+        self
+    }
+
+    /// Sets the ordered sources the origin of a request is read
+    /// from, the first present being used. This defaults to just
+    /// the Origin header.
+    ///
+    /// Sources other than the Origin header are only consulted
+    /// for requests from one of the trusted_proxies().
+    pub fn origin_sources(mut self, sources: Vec<OriginSource>) -> CORSMiddlewareBuilder {
+        self.middleware.origin_sources = sources;
+        self
+    }
+
+    /// Sets the addresses of proxies trusted to pass on the
+    /// origin of a request in other headers.
+    pub fn trusted_proxies<I>(mut self, proxies: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = IpAddr>,
+    {
+        self.middleware.trusted_proxies = proxies.into_iter().collect();
+        self
+    }
+
+    /// Sets whether a request with an empty Origin header is
+    /// handled as though it had no Origin header at all.
+    ///
+    /// By default such requests are refused, so they never
+    /// receive an Access-Control-Allow-Origin header.
+    pub fn empty_origin_as_absent(mut self, as_absent: bool) -> CORSMiddlewareBuilder {
+        self.middleware.empty_origin_as_absent = as_absent;
+        self
+    }
+
+    /// Sets whether the "null" origin, sent by sandboxed iframes,
+    /// file:// pages and some redirects, is allowed and echoed
+    /// back. Any page can make itself send it, so it is refused
+    /// by default, even when no allowlist is configured.
+    pub fn allow_null_origin(mut self, allow: bool) -> CORSMiddlewareBuilder {
+        self.middleware.allow_null_origin = allow;
+        self
+    }
+
+    /// Sets whether the scheme, host and port of a request's
+    /// Referer must match its Origin for the origin to be
+    /// allowed, as a hardening measure against spoofed origins.
+    ///
+    /// Requests without a Referer are let through, as browsers
+    /// leave it out depending on the referrer policy.
+    pub fn require_referer_match(mut self, require: bool) -> CORSMiddlewareBuilder {
+        self.middleware.require_referer_match = require;
+        self
+    }
+
+    /// Sets whether http origins are refused on requests made
+    /// over https, as such a request is a downgrade.
+    ///
+    /// The scheme is taken from the request URI when it is
+    /// absolute, or otherwise from X-Forwarded-Proto on requests
+    /// through a trusted proxy. Requests whose scheme is not
+    /// known are let through.
+    pub fn reject_downgraded_origins(mut self, reject: bool) -> CORSMiddlewareBuilder {
+        self.middleware.reject_downgraded_origins = reject;
+        self
+    }
+
+    /// Sets the schemes request origins may use, which default
+    /// to http and https. Origins using any other scheme, such as
+    /// chrome-extension, are refused whatever else is configured.
+    pub fn allowed_schemes<I, S>(mut self, schemes: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.allowed_schemes = schemes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the request headers sent in Access-Control-Allow-Headers
+    /// on preflight responses, which default to Authorization and
+    /// Content-Type.
+    pub fn allow_headers<I, S>(mut self, headers: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.allow_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Same as allow_headers(), but also allows sending the "*"
+    /// wildcard, see AllowedHeaders for its caveats.
+    pub fn allowed_headers(self, headers: AllowedHeaders) -> CORSMiddlewareBuilder {
+        self.allow_headers(headers.into_list())
+    }
+
+    /// Sets the response headers sent in Access-Control-Expose-Headers
+    /// on actual, non-preflight, responses, so that scripts can read
+    /// them. None are exposed by default.
+    pub fn expose_headers<I, S>(mut self, headers: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.expose_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Same as expose_headers(), but also allows sending the "*"
+    /// wildcard, see ExposeHeaders for its caveats.
+    pub fn exposed_headers(self, headers: ExposeHeaders) -> CORSMiddlewareBuilder {
+        self.expose_headers(headers.into_list())
+    }
+
+    /// Sets whether the allowed headers, merged from the static
+    /// list, path policies and echoed request headers, are
+    /// lowercased, trimmed, deduplicated and sorted before being
+    /// sent, giving a clean and stable value.
+    pub fn canonicalize_allow_headers(mut self, canonicalize: bool) -> CORSMiddlewareBuilder {
+        self.middleware.canonicalize_allow_headers = canonicalize;
+        self
+    }
+
+    /// Caps the total bytes, counting names and values, of the
+    /// headers the middleware adds to a response. When the headers
+    /// built for a request would exceed the cap, the static allowed
+    /// headers and methods are sent in place of those merged from
+    /// path policies and the request.
+    pub fn max_header_bytes(mut self, limit: usize) -> CORSMiddlewareBuilder {
+        self.middleware.max_header_bytes = Some(limit);
+        self
+    }
+
+    /// Sets a hook which is called with the response headers
+    /// after the CORS headers have been set, to change or add
+    /// to them.
+    pub fn after_headers(mut self, hook: AfterHeadersHook) -> CORSMiddlewareBuilder {
+        self.middleware.after_headers = Some(hook);
+        self
+    }
+
+    /// Sets what Access-Control-Allow-Headers value actual,
+    /// non-preflight, responses carry. By default they carry
+    /// none, as browsers only read it from preflights.
+    pub fn actual_allow_headers(mut self, headers: ActualAllowHeaders) -> CORSMiddlewareBuilder {
+        self.middleware.actual_allow_headers = headers;
+        self
+    }
+
+    /// Sets the value sent in Access-Control-Max-Age.
+    pub fn max_age(mut self, max_age: u32) -> CORSMiddlewareBuilder {
+        self.middleware.max_age = MaxAge::Seconds(max_age);
+        self
+    }
+
+    /// Same as max_age(), but also allows turning caching off
+    /// or leaving the header off for the browser default, which
+    /// a None also does.
+    pub fn max_age_policy<M>(mut self, max_age: M) -> CORSMiddlewareBuilder
+    where
+        M: Into<MaxAge>,
+    {
+        self.middleware.max_age = max_age.into();
+        self
+    }
+
+    /// Sets whether CORS-safelisted headers named in a request's
+    /// Access-Control-Request-Headers are added to the allowed
+    /// headers sent back. Any other requested headers still need
+    /// allowing explicitly.
+    pub fn echo_safelisted_headers(mut self, echo: bool) -> CORSMiddlewareBuilder {
+        self.middleware.echo_safelisted_headers = echo;
+        self
+    }
+
+    /// Sets whether every header named in a request's
+    /// Access-Control-Request-Headers is added to the allowed
+    /// headers sent back, rather than just the safelisted ones.
+    ///
+    /// Headers which carry credentials are left out when
+    /// credentials are not allowed.
+    pub fn mirror_request_headers(mut self, mirror: bool) -> CORSMiddlewareBuilder {
+        self.middleware.mirror_request_headers = mirror;
+        self
+    }
+
+    /// Sets whether preflights get their Access-Control-Request-Headers
+    /// value sent back verbatim in Access-Control-Allow-Headers, in
+    /// place of the configured list. Preflights without the header,
+    /// or naming a denied header, get the configured list.
+    pub fn reflect_request_headers(mut self, reflect: bool) -> CORSMiddlewareBuilder {
+        self.middleware.reflect_request_headers = reflect;
+        self
+    }
+
+    /// Sets whether Access-Control-Max-Age is also sent on actual,
+    /// non-preflight, responses. Browsers only cache preflights,
+    /// so by default it is left off the others.
+    pub fn max_age_on_actual(mut self, on_actual: bool) -> CORSMiddlewareBuilder {
+        self.middleware.max_age_on_actual = on_actual;
+        self
+    }
+
+    /// Sets whether Access-Control-Allow-Methods is also sent on
+    /// actual, non-preflight, responses. Browsers only read it
+    /// from preflights, so by default it is left off the others.
+    pub fn methods_on_actual(mut self, on_actual: bool) -> CORSMiddlewareBuilder {
+        self.middleware.methods_on_actual = on_actual;
+        self
+    }
+
+    /// Sets whether preflights get only their Access-Control-Request-Method
+    /// sent back in Access-Control-Allow-Methods, in place of every
+    /// allowed method. Preflights for a method which is not allowed
+    /// get no Access-Control-Allow-Methods at all, so the browser
+    /// fails them.
+    pub fn reflect_request_method(mut self, reflect: bool) -> CORSMiddlewareBuilder {
+        self.middleware.reflect_request_method = reflect;
+        self
+    }
+
+    /// Sets whether preflights carrying
+    /// Access-Control-Request-Private-Network: true, sent by
+    /// Chrome for requests from a public site into a private
+    /// network, get Access-Control-Allow-Private-Network: true.
+    /// Without it such requests are blocked by the browser.
+    ///
+    /// This is off by default.
+    pub fn allow_private_network(mut self, allow: bool) -> CORSMiddlewareBuilder {
+        self.middleware.allow_private_network = allow;
+        self
+    }
+
+    /// Sets the most headers a request may name in
+    /// Access-Control-Request-Headers for any of them to be
+    /// mirrored. Requests naming more get just the static
+    /// allowed headers, so a long list cannot bloat the response.
+    pub fn max_requested_headers(mut self, limit: usize) -> CORSMiddlewareBuilder {
+        self.middleware.max_requested_headers = Some(limit);
+        self
+    }
+
+    /// Sets whether Access-Control-Allow-Credentials is sent,
+    /// letting browsers include cookies and authorization headers
+    /// in cross-origin requests. This is on by default, as it is
+    /// for CORSMiddleware::default(), to keep existing apps
+    /// working, but token based APIs should turn it off rather
+    /// than advertise support they do not need.
+    pub fn allow_credentials(mut self, credentials: bool) -> CORSMiddlewareBuilder {
+        self.middleware.credentials = credentials;
+        self
+    }
+
+    /// Sets whether Origin is added to the Vary header of every
+    /// response, which is needed for caches to keep responses
+    /// apart when the request origin is reflected back.
+    pub fn vary_origin(mut self, vary: bool) -> CORSMiddlewareBuilder {
+        self.middleware.vary_origin = vary;
+        self
+    }
+
+    /// Sets whether Origin is added to the Vary header of responses
+    /// whose Access-Control-Allow-Origin depends on the request,
+    /// which is the case unless a fixed origin or "*" is sent. On
+    /// by default.
+    pub fn vary_reflected_origin(mut self, vary: bool) -> CORSMiddlewareBuilder {
+        self.middleware.vary_reflected_origin = vary;
+        self
+    }
+
+    /// Sets whether CORS headers are sent on responses to requests
+    /// without an Origin header, such as same-origin and non-browser
+    /// ones. Off by default, leaving those responses untouched
+    /// apart from Vary.
+    pub fn emit_without_origin(mut self, emit: bool) -> CORSMiddlewareBuilder {
+        self.middleware.emit_without_origin = emit;
+        self
+    }
+
+    /// Sets whether CORS headers replace those of the same name
+    /// a handler already set on its response. On by default;
+    /// turned off, a handler can set its own, for example a
+    /// different Access-Control-Allow-Origin on one route.
+    pub fn overwrite_existing(mut self, overwrite: bool) -> CORSMiddlewareBuilder {
+        self.middleware.overwrite_existing = overwrite;
+        self
+    }
+
+    /// Sets further tokens added to the Vary header of every
+    /// response, alongside Origin when vary_origin() is on and
+    /// whatever the response already varies on.
+    pub fn extra_vary<I, S>(mut self, tokens: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.extra_vary = tokens.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets a separate Access-Control-Max-Age for preflights
+    /// which name request headers, with max_age() then only
+    /// applying to preflights checking nothing but the method.
+    ///
+    /// Browsers keep a single max age per preflight, so this
+    /// is chosen from what the preflight asked for. A shorter
+    /// value for header driven preflights lets changes to the
+    /// allowed headers reach clients sooner, while method only
+    /// preflights stay cached for longer.
+    pub fn headers_preflight_max_age(mut self, max_age: u32) -> CORSMiddlewareBuilder {
+        self.middleware.headers_preflight_max_age = Some(max_age);
+        self
+    }
+
+    /// Sets how preflights from origins which are not allowed
+    /// are answered. By default they carry on as normal.
+    pub fn disallowed_preflight(mut self, response: DisallowedResponse) -> CORSMiddlewareBuilder {
+        self.middleware.disallowed_preflight = response;
+        self
+    }
+
+    /// Sets how actual requests from origins which are not
+    /// allowed are answered. By default they carry on as normal.
+    pub fn disallowed_actual(mut self, response: DisallowedResponse) -> CORSMiddlewareBuilder {
+        self.middleware.disallowed_actual = response;
+        self
+    }
+
+    /// Sets whether preflights and actual requests from origins
+    /// which are not allowed are answered with a 403 straight
+    /// away, so the handler never runs. Turning it off lets both
+    /// carry on as normal, as they do by default.
+    pub fn reject_disallowed_origins(self, reject: bool) -> CORSMiddlewareBuilder {
+        let response = if reject {
+            DisallowedResponse::Forbidden
+        } else {
+            DisallowedResponse::Continue
+        };
+
+        self.disallowed_preflight(response)
+            .disallowed_actual(response)
+    }
+
+    /// Sets a reporter which is handed a BlockEvent for
+    /// every request whose origin is not allowed.
+    pub fn block_reporter(mut self, reporter: BlockReporter) -> CORSMiddlewareBuilder {
+        self.middleware.block_reporter = Some(reporter);
+        self
+    }
+
+    /// Sets whether the time taken to decide on the origin of each
+    /// request is recorded, as a CorsDecisionTiming in the State
+    /// and in a debug log message.
+    pub fn record_decision_timing(mut self, record: bool) -> CORSMiddlewareBuilder {
+        self.middleware.record_decision_timing = record;
+        self
+    }
+
+    /// Logs blocked requests, at most once per origin within the
+    /// given window. The number of blocks left out is included in
+    /// the next message logged for that origin.
+    ///
+    /// By default blocked requests are not logged.
+    pub fn block_log_window(mut self, window: Duration) -> CORSMiddlewareBuilder {
+        self.middleware.block_log_limiter = Some(BlockLogLimiter::new(window));
+        self
+    }
+
+    /// Sets policies which replace the allowed methods and add
+    /// to the allowed headers for requests to matching paths.
+    /// The first policy whose template matches is used.
+    pub fn path_policies(mut self, policies: Vec<PathPolicy>) -> CORSMiddlewareBuilder {
+        self.middleware.path_policies = policies;
+        self
+    }
+
+    /// Sets whether requests from loopback, private or link
+    /// local addresses skip the middleware entirely, leaving
+    /// their responses without any CORS headers.
+    ///
+    /// The address is that of the connecting client, so behind
+    /// a reverse proxy every request looks internal and this
+    /// should be left off.
+    pub fn skip_private_clients(mut self, skip: bool) -> CORSMiddlewareBuilder {
+        self.middleware.skip_private_clients = skip;
+        self
+    }
+
+    /// Sets whether an OPTIONS request with an Origin but no
+    /// Access-Control-Request-Method is treated as a preflight,
+    /// as some proxies strip that header from preflights. Those
+    /// to the preflight paths are then answered by the
+    /// middleware itself.
+    pub fn lenient_preflights(mut self, lenient: bool) -> CORSMiddlewareBuilder {
+        self.middleware.lenient_preflights = lenient;
+        self
+    }
+
+    /// Sets whether preflights asking for a method the matched
+    /// route does not support are answered with a 405 and an Allow
+    /// header, rather than the usual CORS headers. The route's
+    /// methods are read from the RouteMethods the app puts in the
+    /// State, as they cannot be taken from Gotham's router, and
+    /// preflights without one are unaffected.
+    pub fn reject_unsupported_methods(mut self, reject: bool) -> CORSMiddlewareBuilder {
+        self.middleware.reject_unsupported_methods = reject;
+        self
+    }
+
+    /// Sets the paths for which the middleware answers every
+    /// preflight request itself with a 204, without calling
+    /// the handler. A trailing "*" in a pattern matches any
+    /// remainder of the path, so "/app/*" covers every path
+    /// below "/app/".
+    ///
+    /// By default every path is covered, as with "/*".
+    /// Gotham only runs middleware for routes it has matched,
+    /// so the paths still need an OPTIONS route - a single
+    /// `route.options("/*")` catch-all is enough.
+    pub fn preflight_paths<I, S>(mut self, patterns: I) -> CORSMiddlewareBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.middleware.preflight_paths = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Makes the middleware answer every preflight itself, so
+    /// that middleware added after it in the pipeline, such as
+    /// authentication, never sees them. Same as calling
+    /// preflight_paths() with "/*", which is the default.
+    ///
+    /// The paths still need an OPTIONS route for Gotham to run
+    /// the pipeline at all, see preflight_paths().
+    pub fn terminate_preflights(self) -> CORSMiddlewareBuilder {
+        self.preflight_paths(vec!["/*"])
+    }
+
+    /// Passes every preflight on to the rest of the chain, adding
+    /// the CORS headers to the handler's response as for any other
+    /// request. Same as calling preflight_paths() with no paths.
+    ///
+    /// Route OPTIONS to cors_preflight_handler to have the passed
+    /// on preflights answered as the middleware would answer them.
+    pub fn pass_preflights(self) -> CORSMiddlewareBuilder {
+        self.preflight_paths(Vec::<String>::new())
+    }
+
+    /// Sets a body, and its content type, for the preflight
+    /// responses the middleware generates itself. These are
+    /// sent with a 200 rather than a 204 once a body is set.
+    ///
+    /// By default generated preflight responses are empty.
+    pub fn preflight_body(mut self, body: Vec<u8>, mime: Mime) -> CORSMiddlewareBuilder {
+        self.middleware.preflight_body = Some((body, mime));
+        self
+    }
+
+    /// Sets the status of the empty preflight responses the
+    /// middleware generates itself, for clients which mishandle
+    /// a 204. Defaults to 204 No Content.
+    pub fn preflight_status(mut self, status: StatusCode) -> CORSMiddlewareBuilder {
+        self.middleware.preflight_status = status;
+        self
+    }
+
+    /// Limits the CORS headers to requests whose Content-Type
+    /// is one of the given types, ignoring any parameters such
+    /// as the charset. Requests without a Content-Type are
+    /// still decorated. An empty list, the default, accepts
+    /// every request.
+    pub fn content_types(mut self, content_types: Vec<Mime>) -> CORSMiddlewareBuilder {
+        self.middleware.content_types = content_types;
+        self
+    }
+
+    /// Sends the given version in an X-CORS-Policy-Version
+    /// header alongside the CORS headers, so clients can tell
+    /// which policy served a response while it is being changed.
+    pub fn policy_version(mut self, version: u32) -> CORSMiddlewareBuilder {
+        self.middleware.policy_version = Some(version);
+        self
+    }
+
+    /// Sets whether Access-Control-Allow-Origin lists every
+    /// allowed origin, separated by commas, in place of the
+    /// matching request origin.
+    ///
+    /// This is not compliant with the Fetch standard and
+    /// browsers reject such responses, so it is only meant for
+    /// non-browser clients which read the list. Turning it on is
+    /// logged as a warning when the middleware is built.
+    pub fn list_allowed_origins(mut self, list: bool) -> CORSMiddlewareBuilder {
+        self.middleware.list_allowed_origins = list;
+        self
+    }
+
+    /// Sets a callback which receives the headers the middleware
+    /// added, changed or removed on every response it decorates,
+    /// after any after_headers() hook has run.
+    pub fn header_diff_recorder(mut self, recorder: HeaderDiffRecorder) -> CORSMiddlewareBuilder {
+        self.middleware.header_diff_recorder = Some(recorder);
+        self
+    }
+
+    /// Consumes the builder, returning the configured
+    /// CORSMiddleware.
+    ///
+    /// Entries on both an allow and a deny list are logged as
+    /// warnings, as the deny list takes precedence, as is listing
+    /// the allowed origins in Access-Control-Allow-Origin.
+    ///
+    /// Methods and header names which cannot be sent in a header,
+    /// such as those holding spaces or line breaks, are dropped
+    /// with a warning rather than sent malformed, and a fixed
+    /// origin which cannot be sent is left off responses.
+    pub fn build(mut self) -> CORSMiddleware {
+        self.warn_overlaps();
+        // Overrides built for the middleware this one started
+        // from do not apply to it
+        self.middleware.override_cache = OverrideCache::default();
+        self.middleware.drop_invalid_header_values();
+        self.middleware.precompute_headers();
+
+        if self.middleware.list_allowed_origins {
+            warn!(
+                "Access-Control-Allow-Origin lists every allowed origin, browsers will reject it"
+            );
+        }
+
+        let expose = &self.middleware.expose_headers;
+
+        if self.middleware.credentials && expose.len() == 1 && expose[0] == "*" {
+            warn!(
+                "Access-Control-Expose-Headers \"*\" is ignored on credentialed requests, \
+                 name the headers to expose alongside it"
+            );
+        }
+
+        self.middleware
+    }
+
+    fn warn_overlaps(&self) {
+        let middleware = &self.middleware;
+
+        for denied in &middleware.denied_origins {
+            let denied = denied.to_string();
+
+            let fixed = middleware
+                .origin
+                .as_ref()
+                .is_some_and(|fixed| origin::same_origin(fixed, &denied));
+
+            let allowed = fixed
+                || middleware
+                    .allowed_origins
+                    .iter()
+                    .any(|allowed| allowed.to_string() == denied);
+
+            if allowed {
+                warn!(
+                    "Origin {} is both allowed and denied, it will be denied",
+                    denied
+                );
+            }
+        }
+
+        let policy_headers = middleware
+            .path_policies
+            .iter()
+            .flat_map(|policy| policy.headers());
+
+        for header in middleware.allow_headers.iter().chain(policy_headers) {
+            if middleware
+                .denied_headers
+                .iter()
+                .any(|denied| denied.trim().eq_ignore_ascii_case(header.trim()))
+            {
+                warn!(
+                    "Header {} is both allowed and denied, it will be denied",
+                    header
+                );
+            }
+        }
+    }
+
+    /// Same as build(), but first checks that the fixed origin,
+    /// when one is set, is an absolute origin with a scheme and
+    /// host and no path, query or fragment. The "*" and "null"
+    /// values are let through as they are.
+    ///
+    /// Also fails on the methods and header names build() would
+    /// drop. Allowlisted origins are checked as they are set.
+    pub fn try_build(self) -> Result<CORSMiddleware, CorsError> {
+        self.middleware.check_header_values()?;
+
+        if let Some(ref origin) = self.middleware.origin {
+            if origin != "*" && origin != "null" {
+                origin.parse::<OriginMatcher>()?;
+            }
+        }
+
+        Ok(self.build())
+    }
+}
+
+impl Default for CORSMiddlewareBuilder {
+    fn default() -> CORSMiddlewareBuilder {
+        CORSMiddlewareBuilder::new()
+    }
+}
+
+/// Seeds a builder with the options of an existing
+/// CORSMiddleware, such as a global policy from which a
+/// route specific one is derived.
+impl<'a> From<&'a CORSMiddleware> for CORSMiddlewareBuilder {
+    fn from(existing: &'a CORSMiddleware) -> CORSMiddlewareBuilder {
+        CORSMiddlewareBuilder {
+            middleware: existing.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        assert_eq!(
+            CORSMiddlewareBuilder::new().build(),
+            CORSMiddleware::default()
+        );
+    }
+
+    #[test]
+    fn test_builder_overrides() {
+        let methods = vec![Method::Get, Method::Options];
+        let origin = Some("http://www.example.com".to_string());
+
+        let built = CORSMiddlewareBuilder::new()
+            .strict_allowlist(false)
+            .methods(methods.clone())
+            .origin(origin.clone())
+            .max_age(1000)
+            .build();
+
+        assert_eq!(built, CORSMiddleware::new(methods, origin, 1000));
+    }
+
+    #[test]
+    fn test_builder_from_existing() {
+        let global = CORSMiddlewareBuilder::new()
+            .allowed_origins(vec!["https://app.example.com"])
+            .unwrap()
+            .allow_headers(vec!["X-Api-Key"])
+            .vary_origin(true)
+            .build();
+
+        let derived = CORSMiddlewareBuilder::from(&global).max_age(60).build();
+
+        assert_ne!(derived, global);
+
+        let mut expected = global.clone();
+        expected.max_age = MaxAge::Seconds(60);
+
+        assert_eq!(derived, expected);
+    }
+
+    #[test]
+    fn test_try_build_validates_origin() {
+        let result = CORSMiddlewareBuilder::new()
+            .origin(Some("www.example.com".to_string()))
+            .try_build();
+
+        assert_eq!(
+            result,
+            Err(CorsError::InvalidOrigin {
+                origin: "www.example.com".to_string(),
+                reason: "missing scheme",
+            })
+        );
+
+        let result = CORSMiddlewareBuilder::new()
+            .origin(Some("https://www.example.com/app".to_string()))
+            .try_build();
+
+        assert_eq!(
+            result,
+            Err(CorsError::InvalidOrigin {
+                origin: "https://www.example.com/app".to_string(),
+                reason: "must not contain a path, query or fragment",
+            })
+        );
+
+        assert!(CORSMiddlewareBuilder::new()
+            .origin(Some("https://www.example.com".to_string()))
+            .try_build()
+            .is_ok());
+        assert!(CORSMiddlewareBuilder::new()
+            .origin(Some("*".to_string()))
+            .try_build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_builder_allowed_origin_with_path() {
+        let result = CORSMiddlewareBuilder::new().allowed_origins(vec!["https://www.example.com/"]);
+
+        assert_eq!(
+            result,
+            Err(CorsError::InvalidOrigin {
+                origin: "https://www.example.com/".to_string(),
+                reason: "must not contain a path, query or fragment",
+            })
+        );
+        assert!(CORSMiddlewareBuilder::new()
+            .allowed_origins(vec!["https://www.example.com:8443"])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_builder_invalid_allowed_origin() {
+        let result = CORSMiddlewareBuilder::new().allowed_origins(vec!["www.example.com"]);
+
+        assert_eq!(
+            result,
+            Err(CorsError::InvalidOrigin {
+                origin: "www.example.com".to_string(),
+                reason: "missing scheme",
+            })
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! A plain struct holding the common options of a
+//! CORSMiddleware, for setting them all in one place.
+use hyper::Method;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use error::CorsError;
+use max_age::MaxAge;
+use methods::AllowedMethods;
+use origin;
+use {CORSMiddleware, CORSMiddlewareBuilder};
+
+/// Number of CORSConfig overrides kept built at once. Building
+/// a new one beyond this forgets the one built the longest ago.
+const OVERRIDE_CACHE_CAPACITY: usize = 32;
+
+/// The common options of a CORSMiddleware. Options beyond these
+/// are set through the CORSMiddlewareBuilder.
+///
+/// Every option is optional, and those left as None take the
+/// values of CORSMiddleware::default(), so a config can be
+/// written with only the fields that differ.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham_cors_middleware;
+///
+/// use gotham_cors_middleware::{CORSConfig, CORSMiddleware};
+///
+/// fn main() {
+///     let middleware = CORSMiddleware::from_config(CORSConfig {
+///         origins: Some(vec!["https://app.example.com".to_string()]),
+///         credentials: Some(false),
+///         ..CORSConfig::default()
+///     }).unwrap();
+/// #   drop(middleware);
+/// }
+/// ```
+///
+/// A CORSConfig put into the State by an earlier middleware
+/// overrides the options it holds for that request only, which
+/// scopes the policy to a route without a separate pipeline.
+/// Options left as None keep their configured values, and the
+/// override is ignored when one of its origins is invalid. Each
+/// distinct override is only applied once, with the result kept
+/// for the requests carrying it after.
+///
+/// A fixed origin takes precedence over the allowed origins, so
+/// setting origins without an origin switches to the allowlist,
+/// dropping any fixed origin.
+///
+/// With the "serde" feature a CORSConfig can be read from and
+/// written to any format serde supports. Methods are written as
+/// their names and fields left out are None.
+#[derive(Clone, Debug, Default, PartialEq, StateData)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CORSConfig {
+    /// A fixed origin sent in every Access-Control-Allow-Origin.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub origin: Option<String>,
+    /// The origins reflected back when no fixed origin is set.
+    /// When empty, any origin is reflected unless the allowlist
+    /// is strict, as it is by default with the "production"
+    /// feature.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub origins: Option<Vec<String>>,
+    /// The methods sent in Access-Control-Allow-Methods.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "::serialize::methods", skip_serializing_if = "Option::is_none")
+    )]
+    pub methods: Option<Vec<Method>>,
+    /// The request headers sent in Access-Control-Allow-Headers.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub headers: Option<Vec<String>>,
+    /// The response headers sent in Access-Control-Expose-Headers.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub expose: Option<Vec<String>>,
+    /// Whether Access-Control-Allow-Credentials is sent.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub credentials: Option<bool>,
+    /// The Access-Control-Max-Age sent.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_age: Option<MaxAge>,
+}
+
+/// Takes the common options of a CORSMiddleware. Allowed
+/// browser extensions are left out of the origins.
+impl<'a> From<&'a CORSMiddleware> for CORSConfig {
+    fn from(middleware: &'a CORSMiddleware) -> CORSConfig {
+        CORSConfig {
+            origin: middleware.origin.clone(),
+            origins: Some(
+                middleware
+                    .allowed_origins
+                    .iter()
+                    .filter(|matcher| matcher.extension().is_none())
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            methods: Some(middleware.methods.to_vec()),
+            headers: Some(middleware.allow_headers.clone()),
+            expose: Some(middleware.expose_headers.clone()),
+            credentials: Some(middleware.credentials),
+            max_age: Some(middleware.max_age),
+        }
+    }
+}
+
+impl CORSMiddleware {
+    /// Creates a CORSMiddleware from a CORSConfig.
+    ///
+    /// Returns an error naming the first of the origins which
+    /// is not valid.
+    pub fn from_config(config: CORSConfig) -> Result<CORSMiddleware, CorsError> {
+        CORSMiddlewareBuilder::new()
+            .config(config)
+            .map(CORSMiddlewareBuilder::build)
+    }
+
+    /// Replaces the options the CORSConfig holds, leaving the
+    /// middleware as it was when one of its origins is invalid.
+    ///
+    /// Unlike building, this makes no checks of the combined
+    /// options, so that it is cheap enough to run per request.
+    pub(crate) fn apply_config(&mut self, config: CORSConfig) -> Result<(), CorsError> {
+        if let Some(origins) = config.origins {
+            self.allowed_origins = origin::compile(origins)?;
+            self.origin = None;
+        }
+
+        if config.origin.is_some() {
+            self.origin = config.origin;
+        }
+        if let Some(methods) = config.methods {
+            self.methods = AllowedMethods::new(methods);
+        }
+        if let Some(headers) = config.headers {
+            self.allow_headers = headers;
+        }
+        if let Some(expose) = config.expose {
+            self.expose_headers = expose;
+        }
+        if let Some(credentials) = config.credentials {
+            self.credentials = credentials;
+        }
+        if let Some(max_age) = config.max_age {
+            self.max_age = max_age;
+        }
+
+        self.drop_invalid_header_values();
+        self.precompute_headers();
+
+        Ok(())
+    }
+}
+
+/// The middlewares built from the CORSConfig overrides seen so
+/// far, shared by the clones Gotham makes of a middleware for
+/// each request.
+#[derive(Clone, Default)]
+pub(crate) struct OverrideCache {
+    built: Arc<Mutex<Overrides>>,
+}
+
+type Overrides = VecDeque<(CORSConfig, Arc<CORSMiddleware>)>;
+
+impl OverrideCache {
+    /// Returns the middleware built for the config, building it
+    /// with the given function when it is not kept already.
+    pub(crate) fn get_or_build<F>(&self, config: &CORSConfig, build: F) -> Arc<CORSMiddleware>
+    where
+        F: FnOnce() -> CORSMiddleware,
+    {
+        let mut built = match self.built.lock() {
+            Ok(built) => built,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some((_, middleware)) = built.iter().find(|entry| entry.0 == *config) {
+            return middleware.clone();
+        }
+
+        let middleware = Arc::new(build());
+
+        if built.len() >= OVERRIDE_CACHE_CAPACITY {
+            built.pop_front();
+        }
+        built.push_back((config.clone(), middleware.clone()));
+
+        middleware
+    }
+}
+
+impl fmt::Debug for OverrideCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("OverrideCache { .. }")
+    }
+}
+
+/// The cache only holds what the rest of the middleware
+/// determines, so it plays no part in comparisons.
+impl PartialEq for OverrideCache {
+    fn eq(&self, _: &OverrideCache) -> bool {
+        true
+    }
+}
+
+impl CORSMiddlewareBuilder {
+    /// Sets every option held in the CORSConfig, leaving the
+    /// others as they are.
+    ///
+    /// Returns an error naming the first of the origins which
+    /// is not valid.
+    pub fn config(self, config: CORSConfig) -> Result<CORSMiddlewareBuilder, CorsError> {
+        let mut builder = match config.origins {
+            Some(origins) => self.allowed_origins(origins)?.origin(None),
+            None => self,
+        };
+
+        if config.origin.is_some() {
+            builder = builder.origin(config.origin);
+        }
+        if let Some(methods) = config.methods {
+            builder = builder.methods(methods);
+        }
+        if let Some(headers) = config.headers {
+            builder = builder.allow_headers(headers);
+        }
+        if let Some(expose) = config.expose {
+            builder = builder.expose_headers(expose);
+        }
+        if let Some(credentials) = config.credentials {
+            builder = builder.allow_credentials(credentials);
+        }
+        if let Some(max_age) = config.max_age {
+            builder = builder.max_age_policy(max_age);
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        assert_eq!(
+            CORSMiddleware::from_config(CORSConfig::default()),
+            Ok(CORSMiddleware::default())
+        );
+    }
+
+    #[test]
+    fn test_full_config() {
+        let config = CORSConfig {
+            origin: None,
+            origins: Some(vec![
+                "https://app.example.com".to_string(),
+                "https://admin.example.com".to_string(),
+            ]),
+            methods: Some(vec![Method::Get, Method::Post]),
+            headers: Some(vec!["X-Api-Key".to_string()]),
+            expose: Some(vec!["X-Total-Count".to_string()]),
+            credentials: Some(false),
+            max_age: Some(MaxAge::NoCache),
+        };
+
+        let expected = CORSMiddleware::builder()
+            .allowed_origins(vec!["https://app.example.com", "https://admin.example.com"])
+            .unwrap()
+            .methods(vec![Method::Get, Method::Post])
+            .allow_headers(vec!["X-Api-Key"])
+            .expose_headers(vec!["X-Total-Count"])
+            .allow_credentials(false)
+            .max_age_policy(MaxAge::NoCache)
+            .build();
+
+        assert_eq!(CORSMiddleware::from_config(config), Ok(expected));
+    }
+
+    #[test]
+    fn test_config_from_middleware() {
+        let middleware = CORSMiddleware::from_config(CORSConfig {
+            origins: Some(vec!["https://app.example.com".to_string()]),
+            methods: Some(vec![Method::Get]),
+            credentials: Some(false),
+            ..CORSConfig::default()
+        })
+        .unwrap();
+
+        let config = CORSConfig::from(&middleware);
+
+        assert_eq!(
+            config.origins,
+            Some(vec!["https://app.example.com".to_string()])
+        );
+        assert_eq!(config.methods, Some(vec![Method::Get]));
+        assert_eq!(config.credentials, Some(false));
+        assert_eq!(CORSMiddleware::from_config(config), Ok(middleware));
+    }
+
+    #[test]
+    fn test_partial_config_keeps_other_options() {
+        let configured = CORSMiddleware::builder()
+            .allowed_origins(vec!["https://app.example.com"])
+            .unwrap()
+            .max_age(60)
+            .build();
+
+        let mut middleware = configured.clone();
+        middleware
+            .apply_config(CORSConfig {
+                credentials: Some(false),
+                ..CORSConfig::default()
+            })
+            .unwrap();
+
+        assert!(!middleware.credentials);
+        assert_eq!(middleware.allowed_origins, configured.allowed_origins);
+        assert_eq!(middleware.max_age, configured.max_age);
+        assert!(!middleware.is_origin_allowed("https://evil.example.com"));
+    }
+
+    #[test]
+    fn test_invalid_config_origin() {
+        let config = CORSConfig {
+            origins: Some(vec!["app.example.com".to_string()]),
+            ..CORSConfig::default()
+        };
+
+        assert!(CORSMiddleware::from_config(config.clone()).is_err());
+
+        // An invalid override leaves the middleware as it was
+        let mut middleware = CORSMiddleware::default();
+        assert!(middleware.apply_config(config).is_err());
+        assert_eq!(middleware, CORSMiddleware::default());
+    }
+
+    #[test]
+    fn test_origins_replace_fixed_origin() {
+        let mut middleware = CORSMiddleware::builder()
+            .origin(Some("https://app.example.com".to_string()))
+            .build();
+
+        middleware
+            .apply_config(CORSConfig {
+                origins: Some(vec!["https://admin.example.com".to_string()]),
+                ..CORSConfig::default()
+            })
+            .unwrap();
+
+        assert_eq!(middleware.origin, None);
+        assert!(middleware.is_origin_allowed("https://admin.example.com"));
+        assert!(!middleware.is_origin_allowed("https://app.example.com"));
+    }
+
+    #[test]
+    fn test_override_built_once() {
+        let middleware = CORSMiddleware::builder().build();
+        let config = CORSConfig {
+            origins: Some(vec!["https://app.example.com".to_string()]),
+            ..CORSConfig::default()
+        };
+
+        let first = middleware.overridden_by(&config);
+
+        assert!(Arc::ptr_eq(
+            &first,
+            &middleware.clone().overridden_by(&config)
+        ));
+        assert!(first.is_origin_allowed("https://app.example.com"));
+
+        // A middleware built from another does not share its overrides
+        let derived = CORSMiddlewareBuilder::from(&middleware).max_age(60).build();
+
+        assert!(!Arc::ptr_eq(&first, &derived.overridden_by(&config)));
+    }
+}
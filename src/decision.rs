@@ -0,0 +1,308 @@
+//! The outcome of the CORS checks on a request, for use by
+//! handlers and middleware which apply the headers themselves.
+use gotham::state::{FromState, State};
+use hyper::header::Headers;
+use hyper::Method;
+use std::time::{Duration, Instant};
+use unicase::Ascii;
+
+use {has_multiple_origins, CORSMiddleware, OriginSettings};
+
+/// What a CORSMiddleware decided about a single request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CORSDecision {
+    /// Whether the request is a CORS preflight.
+    pub preflight: bool,
+    /// Whether the request origin is allowed. Requests without
+    /// an origin are always allowed, and those with more than
+    /// one never are.
+    pub allowed: bool,
+    /// The value sent in Access-Control-Allow-Origin, if any.
+    pub origin: Option<String>,
+    /// The CORS headers to add to the response.
+    pub headers: Headers,
+    /// The tokens to add to the Vary header of the response,
+    /// alongside any it already has.
+    pub vary: Vec<String>,
+}
+
+/// The origin checks made on a request, worked out once so
+/// that resolvers and predicates only run once per request.
+#[derive(Clone, Debug)]
+pub(crate) struct OriginCheck {
+    /// The origin the request carries, if any.
+    pub(crate) request_origin: Option<String>,
+    /// Whether the request carries an origin which is not
+    /// allowed, or more than one Origin header.
+    pub(crate) blocked: bool,
+    /// The value to send in Access-Control-Allow-Origin, if any.
+    pub(crate) allow_origin: Option<String>,
+    /// Whether the response gets CORS headers at all.
+    pub(crate) has_origin: bool,
+    /// How long the checks took.
+    pub(crate) elapsed: Duration,
+}
+
+/// The values the CORS headers of a single response are
+/// written from. The header lists stay None while only the
+/// static values are sent, so their precomputed headers are used.
+pub(crate) struct HeaderValues {
+    pub(crate) preflight: bool,
+    pub(crate) origin: Option<String>,
+    pub(crate) settings: OriginSettings,
+    pub(crate) allow_headers: Option<Vec<Ascii<String>>>,
+    pub(crate) methods: Option<Vec<Method>>,
+}
+
+impl CORSMiddleware {
+    /// Makes the origin checks which only look at the request
+    /// headers, as evaluate() does.
+    pub(crate) fn check_headers(&self, headers: &Headers) -> OriginCheck {
+        let started = Instant::now();
+        let request_origin = self.request_origin(headers, false);
+
+        let allowed = request_origin.as_ref().is_some_and(|origin| {
+            self.is_origin_allowed(origin) && self.passes_request_checks(headers, origin)
+        });
+
+        OriginCheck {
+            blocked: has_multiple_origins(headers) || (request_origin.is_some() && !allowed),
+            allow_origin: self.allow_origin_for(headers, request_origin.clone(), allowed),
+            has_origin: self.has_origin(headers, request_origin.as_ref()),
+            request_origin,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Makes the origin checks on a request, taking the allowed
+    /// origins from a resolver when one is configured.
+    pub(crate) fn check_origin(&self, state: &State) -> OriginCheck {
+        let started = Instant::now();
+        let headers = Headers::borrow_from(state);
+        let trusted = self.is_trusted_proxy(state);
+        let request_origin = self.request_origin(headers, trusted);
+
+        let downgraded = request_origin
+            .as_ref()
+            .is_some_and(|origin| self.is_downgraded(state, origin, trusted));
+
+        let allowed = !downgraded
+            && request_origin
+                .as_ref()
+                .is_some_and(|origin| self.is_request_origin_allowed(state, origin));
+
+        let allow_origin = if downgraded {
+            None
+        } else if self.has_resolver() {
+            request_origin.clone().filter(|_| allowed)
+        } else {
+            self.allow_origin_for(headers, request_origin.clone(), allowed)
+        };
+
+        OriginCheck {
+            blocked: has_multiple_origins(headers) || (request_origin.is_some() && !allowed),
+            allow_origin,
+            has_origin: self.has_origin(headers, request_origin.as_ref()),
+            request_origin,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Runs the CORS checks against the headers and method of a
+    /// request, without touching any response.
+    ///
+    /// Only the request itself is looked at, so path policies,
+    /// origin resolvers and trusted proxies play no part. Use the
+    /// middleware itself where those are needed.
+    ///
+    /// Example of use:
+    /// ```rust
+    /// extern crate gotham_cors_middleware;
+    /// extern crate hyper;
+    ///
+    /// use gotham_cors_middleware::CORSMiddleware;
+    /// use hyper::header::{Headers, Origin};
+    /// use hyper::Method;
+    ///
+    /// fn main() {
+    ///     let mut headers = Headers::new();
+    ///     headers.set(Origin::new("https", "app.example.com", None));
+    ///
+    ///     let middleware = CORSMiddleware::builder()
+    ///         .allowed_origins(vec!["https://app.example.com"])
+    ///         .unwrap()
+    ///         .build();
+    ///
+    ///     let decision = middleware.evaluate(&headers, &Method::Get);
+    ///
+    ///     assert!(decision.allowed);
+    ///     assert!(!decision.preflight);
+    /// }
+    /// ```
+    pub fn evaluate(&self, headers: &Headers, method: &Method) -> CORSDecision {
+        let check = self.check_headers(headers);
+
+        let mut decision = self.decide(
+            headers,
+            self.is_preflight_request(method, headers),
+            None,
+            check.allow_origin,
+            !check.blocked,
+        );
+
+        if !check.has_origin {
+            decision.omit_headers();
+        }
+
+        decision
+    }
+}
+
+impl CORSDecision {
+    /// Drops the CORS headers for a request without an origin,
+    /// varying on Origin so that caches do not serve the response
+    /// to cross-origin requests, which would get the headers.
+    pub(crate) fn omit_headers(&mut self) {
+        self.origin = None;
+        self.headers = Headers::new();
+
+        if !self
+            .vary
+            .iter()
+            .any(|token| token.eq_ignore_ascii_case("Origin"))
+        {
+            self.vary.insert(0, "Origin".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::{
+        AccessControlAllowHeaders, AccessControlAllowOrigin, AccessControlRequestMethod, Origin,
+    };
+
+    fn request_headers(origin: Option<&str>, preflight: bool) -> Headers {
+        let mut headers = Headers::new();
+
+        if let Some(origin) = origin {
+            headers.set_raw("Origin", origin);
+        }
+        if preflight {
+            headers.set(AccessControlRequestMethod(Method::Put));
+        }
+
+        headers
+    }
+
+    #[test]
+    fn test_evaluate_preflight() {
+        let middleware = CORSMiddleware::builder().strict_allowlist(false).build();
+        let headers = request_headers(Some("https://app.example.com"), true);
+
+        let decision = middleware.evaluate(&headers, &Method::Options);
+
+        assert!(decision.preflight);
+        assert!(decision.allowed);
+        assert_eq!(decision.origin, Some("https://app.example.com".to_string()));
+        assert!(decision.headers.has::<AccessControlAllowHeaders>());
+    }
+
+    #[test]
+    fn test_evaluate_options_without_request_method() {
+        let middleware = CORSMiddleware::default();
+        let headers = request_headers(Some("https://app.example.com"), false);
+
+        assert!(!middleware.evaluate(&headers, &Method::Options).preflight);
+        assert!(
+            !middleware
+                .evaluate(&request_headers(None, true), &Method::Options)
+                .preflight
+        );
+    }
+
+    #[test]
+    fn test_evaluate_actual_request() {
+        let middleware = CORSMiddleware::builder().strict_allowlist(false).build();
+        let headers = request_headers(Some("https://app.example.com"), false);
+
+        let decision = middleware.evaluate(&headers, &Method::Post);
+
+        assert!(!decision.preflight);
+        assert_eq!(
+            decision.headers.get::<AccessControlAllowOrigin>(),
+            Some(&AccessControlAllowOrigin::Value(
+                "https://app.example.com".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_disallowed_origin() {
+        let middleware = CORSMiddleware::builder()
+            .allowed_origins(vec!["https://app.example.com"])
+            .unwrap()
+            .build();
+        let headers = request_headers(Some("https://evil.example.com"), true);
+
+        let decision = middleware.evaluate(&headers, &Method::Options);
+
+        assert!(decision.preflight);
+        assert!(!decision.allowed);
+        assert_eq!(decision.origin, None);
+        assert!(!decision.headers.has::<AccessControlAllowOrigin>());
+    }
+
+    #[test]
+    fn test_evaluate_without_origin() {
+        let middleware = CORSMiddleware::builder()
+            .allowed_origins(vec!["https://app.example.com"])
+            .unwrap()
+            .vary_origin(true)
+            .build();
+
+        let decision = middleware.evaluate(&request_headers(None, false), &Method::Get);
+
+        assert!(decision.allowed);
+        assert_eq!(decision.origin, None);
+        assert_eq!(decision.vary, vec!["Origin".to_string()]);
+    }
+
+    #[test]
+    fn test_evaluate_without_origin_omits_headers() {
+        let headers = request_headers(None, false);
+
+        let decision = CORSMiddleware::builder()
+            .strict_allowlist(false)
+            .build()
+            .evaluate(&headers, &Method::Get);
+
+        assert_eq!(decision.origin, None);
+        assert_eq!(decision.headers, Headers::new());
+        assert_eq!(decision.vary, vec!["Origin".to_string()]);
+
+        let decision = CORSMiddleware::builder()
+            .strict_allowlist(false)
+            .emit_without_origin(true)
+            .build()
+            .evaluate(&headers, &Method::Get);
+
+        assert_eq!(
+            decision.headers.get::<AccessControlAllowOrigin>(),
+            Some(&AccessControlAllowOrigin::Any)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_matches_origin_header() {
+        let middleware = CORSMiddleware::builder().strict_allowlist(false).build();
+        let mut headers = Headers::new();
+        headers.set(Origin::new("http", "localhost", Some(3000)));
+
+        let decision = middleware.evaluate(&headers, &Method::Delete);
+
+        assert_eq!(decision.origin, Some("http://localhost:3000".to_string()));
+    }
+}
@@ -0,0 +1,44 @@
+//! What the middleware does with requests from origins
+//! it does not allow.
+use std::fmt;
+use std::str::FromStr;
+
+/// How a request from an origin which is not allowed is
+/// answered. Preflights and actual requests are configured
+/// separately.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DisallowedResponse {
+    /// The request carries on as normal, with every CORS header
+    /// but Access-Control-Allow-Origin, so the browser blocks it.
+    #[default]
+    Continue,
+    /// The request carries on as normal, without any CORS
+    /// headers, so the browser blocks it.
+    OmitHeaders,
+    /// The request is answered with a 403 straight away,
+    /// without the rest of the chain running.
+    Forbidden,
+}
+
+impl fmt::Display for DisallowedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DisallowedResponse::Continue => "continue",
+            DisallowedResponse::OmitHeaders => "omit_headers",
+            DisallowedResponse::Forbidden => "forbidden",
+        })
+    }
+}
+
+impl FromStr for DisallowedResponse {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<DisallowedResponse, ()> {
+        match value {
+            "continue" => Ok(DisallowedResponse::Continue),
+            "omit_headers" => Ok(DisallowedResponse::OmitHeaders),
+            "forbidden" => Ok(DisallowedResponse::Forbidden),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Errors produced while configuring a CORSMiddleware.
+use std::error::Error;
+use std::fmt;
+
+/// Describes why a CORSMiddleware could not be configured.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CorsError {
+    /// A configured origin could not be parsed.
+    InvalidOrigin {
+        /// The offending origin, as it was configured.
+        origin: String,
+        /// Why the origin was rejected.
+        reason: &'static str,
+    },
+    /// An OpenAPI document did not have the expected structure.
+    InvalidOpenApi(&'static str),
+    /// A source of the request origin could not be parsed.
+    InvalidOriginSource(&'static str),
+    /// A line of a config string could not be read.
+    InvalidConfig {
+        /// The number of the offending line, starting from 1.
+        line: usize,
+        /// Why the line was rejected.
+        reason: &'static str,
+    },
+    /// A configured method, header name or origin cannot be
+    /// sent in the header it is meant for.
+    InvalidHeaderValue {
+        /// The header the value is meant for.
+        header: &'static str,
+        /// The offending value, as it was configured.
+        value: String,
+    },
+}
+
+impl fmt::Display for CorsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CorsError::InvalidOrigin { ref origin, reason } => {
+                write!(f, "invalid origin {:?}: {}", origin, reason)
+            }
+            CorsError::InvalidOpenApi(reason) => write!(f, "invalid OpenAPI document: {}", reason),
+            CorsError::InvalidOriginSource(reason) => {
+                write!(f, "invalid origin source: {}", reason)
+            }
+            CorsError::InvalidConfig { line, reason } => {
+                write!(f, "invalid config on line {}: {}", line, reason)
+            }
+            CorsError::InvalidHeaderValue { header, ref value } => {
+                write!(f, "invalid value {:?} for {}", value, header)
+            }
+        }
+    }
+}
+
+impl Error for CorsError {}
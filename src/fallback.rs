@@ -0,0 +1,49 @@
+//! A handler for paths no other route matches, so that
+//! their 404 responses still pass through the middleware.
+use gotham::http::response::create_response;
+use gotham::state::State;
+use hyper::{Response, StatusCode};
+
+/// Responds with an empty 404.
+///
+/// Gotham answers requests matching no route itself, without
+/// running any middleware, so those responses carry no CORS
+/// headers and a browser hides them from the page. Routing
+/// every remaining path here instead, through the pipeline
+/// holding the CORSMiddleware, lets front ends read the 404.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham;
+/// extern crate gotham_cors_middleware;
+/// extern crate hyper;
+///
+/// use gotham::pipeline::new_pipeline;
+/// use gotham::pipeline::single::single_pipeline;
+/// use gotham::router::builder::*;
+/// use gotham::router::Router;
+/// use gotham_cors_middleware::{not_found, CORSMiddleware};
+/// use hyper::Method;
+///
+/// pub fn router() -> Router {
+///     let (chain, pipeline) = single_pipeline(
+///         new_pipeline()
+///             .add(CORSMiddleware::default())
+///             .build()
+///     );
+///
+///     build_router(chain, pipeline, |route| {
+///         // Routes
+///
+///         route
+///             .request(vec![Method::Get, Method::Post, Method::Options], "/*")
+///             .to(not_found);
+///     })
+/// }
+/// # fn main() { drop(router()); }
+/// ```
+pub fn not_found(state: State) -> (State, Response) {
+    let response = create_response(&state, StatusCode::NotFound, None);
+
+    (state, response)
+}
@@ -0,0 +1,169 @@
+//! Configuration of the header lists a CORSMiddleware sends.
+use gotham::state::State;
+use hyper::header::{AccessControlAllowHeaders, AccessControlAllowMethods, Headers, Raw};
+use hyper::Method;
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use unicase::Ascii;
+
+use same_arc;
+
+/// The Access-Control-Allow-Headers value sent on actual,
+/// non-preflight, responses.
+///
+/// Only preflight responses need the header, so it is left
+/// off by default, but some legacy clients read it from other
+/// responses too.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ActualAllowHeaders {
+    /// Send the same value as a preflight to the same path.
+    SameAsPreflight,
+    /// Leave the header off.
+    #[default]
+    Omit,
+    /// Send the given header names.
+    List(Vec<String>),
+}
+
+/// The request headers a preflight response allows, as an
+/// alternative to listing them all.
+///
+/// The "*" wildcard never covers Authorization, which has to
+/// be named, and on credentialed requests browsers take it as
+/// a header literally named "*".
+#[derive(Clone, Debug, PartialEq)]
+pub enum AllowedHeaders {
+    /// Send "*", allowing any header but Authorization.
+    Any,
+    /// Send "*" along with Authorization.
+    AnyWithAuthorization,
+    /// Send the given header names.
+    List(Vec<String>),
+}
+
+impl AllowedHeaders {
+    pub(crate) fn into_list(self) -> Vec<String> {
+        match self {
+            AllowedHeaders::Any => vec!["*".to_string()],
+            AllowedHeaders::AnyWithAuthorization => {
+                vec!["*".to_string(), "Authorization".to_string()]
+            }
+            AllowedHeaders::List(headers) => headers,
+        }
+    }
+}
+
+/// The response headers an actual response exposes to scripts,
+/// as an alternative to listing them all.
+///
+/// Browsers ignore the "*" wildcard on credentialed requests,
+/// exposing only the headers named alongside it, so the
+/// middleware leaves it off those responses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExposeHeaders {
+    /// Send "*", exposing every header on requests without
+    /// credentials and none on those with them.
+    Any,
+    /// Send "*" along with the given header names, which are
+    /// all that is exposed on credentialed requests.
+    AnyOr(Vec<String>),
+    /// Send the given header names.
+    List(Vec<String>),
+}
+
+impl ExposeHeaders {
+    pub(crate) fn into_list(self) -> Vec<String> {
+        match self {
+            ExposeHeaders::Any => vec!["*".to_string()],
+            ExposeHeaders::AnyOr(headers) => {
+                let mut list = vec!["*".to_string()];
+                list.extend(headers);
+                list
+            }
+            ExposeHeaders::List(headers) => headers,
+        }
+    }
+}
+
+/// The Access-Control-Allow-Methods and static
+/// Access-Control-Allow-Headers values, formatted once when the
+/// middleware is built so that requests only clone them.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PrecomputedHeaders {
+    pub(crate) allow_methods: Raw,
+    pub(crate) allow_headers: Raw,
+}
+
+impl PrecomputedHeaders {
+    pub(crate) fn new(methods: &[Method], allow_headers: Vec<Ascii<String>>) -> PrecomputedHeaders {
+        PrecomputedHeaders {
+            allow_methods: Raw::from(AccessControlAllowMethods(methods.to_vec()).to_string()),
+            allow_headers: Raw::from(AccessControlAllowHeaders(allow_headers).to_string()),
+        }
+    }
+}
+
+impl Default for PrecomputedHeaders {
+    fn default() -> PrecomputedHeaders {
+        PrecomputedHeaders::new(&[], Vec::new())
+    }
+}
+
+/// A user supplied function called with the response headers
+/// once the middleware has set the CORS headers, to inspect or
+/// change them with the State of the request at hand.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham;
+/// extern crate gotham_cors_middleware;
+/// extern crate hyper;
+///
+/// use gotham::state::State;
+/// use gotham_cors_middleware::{AfterHeadersHook, CORSMiddleware};
+/// use hyper::header::Headers;
+///
+/// fn main() {
+///     let middleware = CORSMiddleware::builder()
+///         .after_headers(AfterHeadersHook::new(|_: &State, headers: &mut Headers| {
+///             headers.set_raw("X-Cors-Policy", "default");
+///         }))
+///         .build();
+/// #   drop(middleware);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct AfterHeadersHook {
+    hook: Arc<HookFn>,
+}
+
+type HookFn = dyn Fn(&State, &mut Headers) + Send + Sync + RefUnwindSafe;
+
+impl AfterHeadersHook {
+    /// Creates an AfterHeadersHook from the given function.
+    pub fn new<F>(hook: F) -> AfterHeadersHook
+    where
+        F: Fn(&State, &mut Headers) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        AfterHeadersHook {
+            hook: Arc::new(hook),
+        }
+    }
+
+    pub(crate) fn call(&self, state: &State, headers: &mut Headers) {
+        (self.hook)(state, headers)
+    }
+}
+
+impl fmt::Debug for AfterHeadersHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AfterHeadersHook { .. }")
+    }
+}
+
+impl PartialEq for AfterHeadersHook {
+    fn eq(&self, other: &AfterHeadersHook) -> bool {
+        same_arc(&self.hook, &other.hook)
+    }
+}
@@ -0,0 +1,86 @@
+//! How long browsers may cache the result of a preflight.
+use std::fmt;
+use std::str::FromStr;
+
+/// The Access-Control-Max-Age sent on responses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaxAge {
+    /// Send the given number of seconds.
+    Seconds(u32),
+    /// Send 0, so browsers do not cache preflights at all.
+    NoCache,
+    /// Leave the header off, so browsers use their own
+    /// default, typically 5 seconds.
+    BrowserDefault,
+}
+
+impl MaxAge {
+    /// The value of the header to send, if any.
+    pub(crate) fn seconds(self) -> Option<u32> {
+        match self {
+            MaxAge::Seconds(seconds) => Some(seconds),
+            MaxAge::NoCache => Some(0),
+            MaxAge::BrowserDefault => None,
+        }
+    }
+}
+
+impl From<u32> for MaxAge {
+    fn from(seconds: u32) -> MaxAge {
+        MaxAge::Seconds(seconds)
+    }
+}
+
+/// None leaves the header off, as BrowserDefault does.
+impl From<Option<u32>> for MaxAge {
+    fn from(seconds: Option<u32>) -> MaxAge {
+        seconds.map_or(MaxAge::BrowserDefault, MaxAge::Seconds)
+    }
+}
+
+impl fmt::Display for MaxAge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MaxAge::Seconds(seconds) => write!(f, "{}", seconds),
+            MaxAge::NoCache => f.write_str("no_cache"),
+            MaxAge::BrowserDefault => f.write_str("browser_default"),
+        }
+    }
+}
+
+impl FromStr for MaxAge {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<MaxAge, ()> {
+        match value {
+            "no_cache" => Ok(MaxAge::NoCache),
+            "browser_default" => Ok(MaxAge::BrowserDefault),
+            seconds => seconds.parse().map(MaxAge::Seconds).map_err(|_| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for max_age in &[
+            MaxAge::Seconds(600),
+            MaxAge::NoCache,
+            MaxAge::BrowserDefault,
+        ] {
+            assert_eq!(max_age.to_string().parse(), Ok(*max_age));
+        }
+
+        assert_eq!("soon".parse::<MaxAge>(), Err(()));
+    }
+
+    #[test]
+    fn test_from_option() {
+        assert_eq!(MaxAge::from(Some(600)), MaxAge::Seconds(600));
+        assert_eq!(MaxAge::from(None), MaxAge::BrowserDefault);
+        assert_eq!(MaxAge::from(None).seconds(), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Storage of the methods a CORSMiddleware allows.
+use hyper::Method;
+use std::collections::HashSet;
+
+use CORSMiddleware;
+
+/// The allowed methods, kept both in order for the
+/// Access-Control-Allow-Methods header and as a set for
+/// checking Access-Control-Request-Method.
+#[derive(Clone, Debug)]
+pub(crate) struct AllowedMethods {
+    ordered: Vec<Method>,
+    lookup: HashSet<Method>,
+}
+
+impl AllowedMethods {
+    /// Keeps the methods in the given order, dropping any
+    /// repeats after the first.
+    pub(crate) fn new(methods: Vec<Method>) -> AllowedMethods {
+        let mut ordered = Vec::with_capacity(methods.len());
+        let mut lookup = HashSet::with_capacity(methods.len());
+
+        for method in methods {
+            if lookup.insert(method.clone()) {
+                ordered.push(method);
+            }
+        }
+
+        AllowedMethods { ordered, lookup }
+    }
+
+    /// Orders the methods by name, as a set has no order of
+    /// its own to keep.
+    pub(crate) fn from_set(methods: HashSet<Method>) -> AllowedMethods {
+        let mut ordered: Vec<Method> = methods.iter().cloned().collect();
+        ordered.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+
+        AllowedMethods {
+            ordered,
+            lookup: methods,
+        }
+    }
+
+    pub(crate) fn contains(&self, method: &Method) -> bool {
+        self.lookup.contains(method)
+    }
+
+    pub(crate) fn as_slice(&self) -> &[Method] {
+        &self.ordered
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<Method> {
+        self.ordered.clone()
+    }
+}
+
+impl PartialEq for AllowedMethods {
+    fn eq(&self, other: &AllowedMethods) -> bool {
+        self.ordered == other.ordered
+    }
+}
+
+impl PartialEq<Vec<Method>> for AllowedMethods {
+    fn eq(&self, other: &Vec<Method>) -> bool {
+        self.ordered == *other
+    }
+}
+
+impl CORSMiddleware {
+    /// Returns true when the method is one of the allowed
+    /// methods, such as one named in a preflight's
+    /// Access-Control-Request-Method header.
+    pub fn allows_method(&self, method: &Method) -> bool {
+        self.methods.contains(method)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_membership() {
+        let methods = AllowedMethods::new(vec![Method::Get, Method::Post]);
+
+        assert!(methods.contains(&Method::Get));
+        assert!(methods.contains(&Method::Post));
+        assert!(!methods.contains(&Method::Delete));
+        assert!(!methods.contains(&Method::Extension("PURGE".to_string())));
+    }
+
+    #[test]
+    fn test_allows_method() {
+        let middleware = CORSMiddleware::builder()
+            .methods(vec![Method::Get, Method::Patch])
+            .build();
+
+        assert!(middleware.allows_method(&Method::Patch));
+        assert!(!middleware.allows_method(&Method::Put));
+    }
+
+    #[test]
+    fn test_order_kept() {
+        let methods =
+            AllowedMethods::new(vec![Method::Put, Method::Get, Method::Put, Method::Delete]);
+
+        assert_eq!(methods, vec![Method::Put, Method::Get, Method::Delete]);
+    }
+
+    #[test]
+    fn test_set_ordered_by_name() {
+        let set: HashSet<Method> = vec![Method::Post, Method::Delete, Method::Get, Method::Put]
+            .into_iter()
+            .collect();
+
+        for _ in 0..10 {
+            assert_eq!(
+                AllowedMethods::from_set(set.clone()),
+                vec![Method::Delete, Method::Get, Method::Post, Method::Put]
+            );
+        }
+    }
+}
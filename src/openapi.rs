@@ -0,0 +1,177 @@
+//! Derives path scoped CORS policies from an OpenAPI document.
+//!
+//! Only available with the "openapi" feature enabled.
+use hyper::Method;
+use serde_json::Value;
+
+use error::CorsError;
+use path::PathPolicy;
+use {CORSMiddleware, CORSMiddlewareBuilder};
+
+/// The operations of a path item, in the order their
+/// methods are listed in the resulting policy.
+const OPERATIONS: [(&str, Method); 8] = [
+    ("get", Method::Get),
+    ("put", Method::Put),
+    ("post", Method::Post),
+    ("delete", Method::Delete),
+    ("options", Method::Options),
+    ("head", Method::Head),
+    ("patch", Method::Patch),
+    ("trace", Method::Trace),
+];
+
+impl CORSMiddleware {
+    /// Creates a CORSMiddleware from the default values, with a
+    /// PathPolicy for every path of the given OpenAPI document.
+    ///
+    /// The document is expected to have been parsed already,
+    /// so YAML documents can be read through serde_yaml into a
+    /// serde_json Value first.
+    pub fn from_openapi(document: &Value) -> Result<CORSMiddleware, CorsError> {
+        CORSMiddlewareBuilder::new()
+            .openapi(document)
+            .map(CORSMiddlewareBuilder::build)
+    }
+}
+
+impl CORSMiddlewareBuilder {
+    /// Sets a PathPolicy for every path of the given OpenAPI
+    /// document, allowing the methods it has operations for and
+    /// the header parameters those operations take.
+    pub fn openapi(self, document: &Value) -> Result<CORSMiddlewareBuilder, CorsError> {
+        Ok(self.path_policies(path_policies(document)?))
+    }
+}
+
+/// Reads a PathPolicy for each entry of the document's paths.
+pub(crate) fn path_policies(document: &Value) -> Result<Vec<PathPolicy>, CorsError> {
+    let paths = match document.get("paths").and_then(Value::as_object) {
+        Some(paths) => paths,
+        None => return Err(CorsError::InvalidOpenApi("missing paths object")),
+    };
+
+    let mut policies = Vec::with_capacity(paths.len());
+
+    for (template, item) in paths {
+        let mut methods = Vec::new();
+        let mut headers = header_parameters(item);
+
+        for &(name, ref method) in OPERATIONS.iter() {
+            if let Some(operation) = item.get(name) {
+                methods.push(method.clone());
+
+                for header in header_parameters(operation) {
+                    if !headers
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(&header))
+                    {
+                        headers.push(header);
+                    }
+                }
+            }
+        }
+
+        policies.push(PathPolicy::new(template.clone(), methods, headers));
+    }
+
+    Ok(policies)
+}
+
+/// Names of the header parameters of a path item or operation.
+fn header_parameters(item: &Value) -> Vec<String> {
+    let parameters = match item.get("parameters").and_then(Value::as_array) {
+        Some(parameters) => parameters,
+        None => return Vec::new(),
+    };
+
+    parameters
+        .iter()
+        .filter(|parameter| parameter.get("in").and_then(Value::as_str) == Some("header"))
+        .filter_map(|parameter| parameter.get("name").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json;
+
+    const FIXTURE: &str = r#"{
+        "openapi": "3.0.0",
+        "info": { "title": "Users", "version": "1.0.0" },
+        "paths": {
+            "/users": {
+                "get": {
+                    "parameters": [
+                        { "name": "X-Request-Id", "in": "header" },
+                        { "name": "page", "in": "query" }
+                    ]
+                },
+                "post": {}
+            },
+            "/users/{id}": {
+                "parameters": [
+                    { "name": "id", "in": "path" },
+                    { "name": "X-Api-Key", "in": "header" }
+                ],
+                "delete": {},
+                "get": {
+                    "parameters": [{ "name": "x-api-key", "in": "header" }]
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_path_policies_from_openapi() {
+        let document: Value = serde_json::from_str(FIXTURE).unwrap();
+
+        let policies = path_policies(&document).unwrap();
+
+        assert_eq!(
+            policies,
+            vec![
+                PathPolicy::new(
+                    "/users",
+                    vec![Method::Get, Method::Post],
+                    vec!["X-Request-Id".to_string()],
+                ),
+                PathPolicy::new(
+                    "/users/{id}",
+                    vec![Method::Get, Method::Delete],
+                    vec!["X-Api-Key".to_string()],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_openapi() {
+        let document: Value = serde_json::from_str(FIXTURE).unwrap();
+
+        let middleware = CORSMiddleware::from_openapi(&document).unwrap();
+
+        assert_eq!(
+            middleware.path_policy("/users/42").map(PathPolicy::methods),
+            Some(&[Method::Get, Method::Delete][..])
+        );
+        assert_eq!(
+            middleware.path_policy("/users").map(PathPolicy::methods),
+            Some(&[Method::Get, Method::Post][..])
+        );
+        assert_eq!(middleware.path_policy("/accounts"), None);
+    }
+
+    #[test]
+    fn test_missing_paths() {
+        let document: Value = serde_json::from_str(r#"{ "openapi": "3.0.0" }"#).unwrap();
+
+        assert_eq!(
+            CORSMiddleware::from_openapi(&document),
+            Err(CorsError::InvalidOpenApi("missing paths object"))
+        );
+    }
+}
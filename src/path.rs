@@ -0,0 +1,126 @@
+//! Policies applying to some request paths only.
+use hyper::Method;
+
+/// The methods the route matching a request supports, put into
+/// the State ahead of the CORSMiddleware by the app, such as from
+/// a middleware in the route's own pipeline.
+///
+/// With reject_unsupported_methods() turned on, preflights asking
+/// for any other method are answered with a 405.
+///
+/// Gotham does not tell middleware which methods the matched
+/// route was built with, so these are not taken from the router.
+/// The app has to list each route's methods again when putting
+/// a RouteMethods into the State, and preflights to routes left
+/// without one are answered as usual.
+#[derive(Clone, Debug, PartialEq, StateData)]
+pub struct RouteMethods {
+    /// The methods the route supports.
+    pub methods: Vec<Method>,
+}
+
+/// The allowed methods and headers for requests whose path
+/// matches a template such as "/users/{id}", where each
+/// "{...}" segment matches any single path segment.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham_cors_middleware;
+/// extern crate hyper;
+///
+/// use gotham_cors_middleware::{CORSMiddleware, PathPolicy};
+/// use hyper::Method;
+///
+/// fn main() {
+///     let middleware = CORSMiddleware::builder()
+///         .path_policies(vec![PathPolicy::new(
+///             "/users/{id}",
+///             vec![Method::Get, Method::Delete],
+///             vec!["X-Api-Key".to_string()],
+///         )])
+///         .build();
+/// #   drop(middleware);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPolicy {
+    template: String,
+    methods: Vec<Method>,
+    headers: Vec<String>,
+}
+
+impl PathPolicy {
+    /// Creates a PathPolicy allowing the given methods and
+    /// request headers on paths matching the template.
+    pub fn new<S: Into<String>>(
+        template: S,
+        methods: Vec<Method>,
+        headers: Vec<String>,
+    ) -> PathPolicy {
+        PathPolicy {
+            template: template.into(),
+            methods,
+            headers,
+        }
+    }
+
+    /// The path template the policy applies to.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The methods allowed on matching paths.
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// The request headers allowed on matching paths.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Returns true when the request path matches the template.
+    pub fn matches(&self, path: &str) -> bool {
+        let mut template = self.template.split('/');
+        let mut path = path.split('/');
+
+        loop {
+            match (template.next(), path.next()) {
+                (Some(expected), Some(segment)) => {
+                    let is_parameter = expected.starts_with('{') && expected.ends_with('}');
+
+                    if is_parameter && segment.is_empty() {
+                        return false;
+                    }
+
+                    if !is_parameter && expected != segment {
+                        return false;
+                    }
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_matches() {
+        let policy = PathPolicy::new("/users/{id}/posts", vec![Method::Get], vec![]);
+
+        assert!(policy.matches("/users/42/posts"));
+        assert!(!policy.matches("/users/42"));
+        assert!(!policy.matches("/users//posts"));
+        assert!(!policy.matches("/users/42/posts/1"));
+        assert!(!policy.matches("/accounts/42/posts"));
+
+        let policy = PathPolicy::new("/users", vec![Method::Get], vec![]);
+
+        assert!(policy.matches("/users"));
+        assert!(!policy.matches("/users/"));
+    }
+}
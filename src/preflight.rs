@@ -0,0 +1,89 @@
+//! A handler answering the preflights a CORSMiddleware passes
+//! on, so that no route needs an OPTIONS handler of its own.
+use gotham::state::State;
+use hyper::{Response, StatusCode};
+use std::sync::Arc;
+
+use decision::OriginCheck;
+use {CORSMiddleware, NO_CORS_HEADER};
+
+/// The policy the CORSMiddleware put into the State of a
+/// preflight, along with its checks of the request origin, for
+/// cors_preflight_handler to answer with.
+#[derive(StateData)]
+pub(crate) struct PreflightPolicy(pub(crate) Arc<CORSMiddleware>, pub(crate) OriginCheck);
+
+/// Answers a preflight with the headers of the CORSMiddleware
+/// in the pipeline, as it would answer one to a path set with
+/// preflight_paths().
+///
+/// This is for a middleware built with pass_preflights(), which
+/// lets preflights through the rest of the pipeline, such as to
+/// have them logged, rather than answering them itself as it
+/// does by default. Route OPTIONS here so that no handler needs
+/// to answer preflights itself. Other OPTIONS requests get an
+/// empty 204, to which the middleware adds its headers as it
+/// would to any response.
+///
+/// Gotham only runs middleware for routes it has matched, and
+/// answers OPTIONS to a routed path with a 405 unless that path
+/// routes OPTIONS too. A catch-all OPTIONS route covers the paths
+/// with no routes, but paths with routes of their own each need
+/// OPTIONS routed here, whether or not preflights are passed on.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham;
+/// extern crate gotham_cors_middleware;
+/// extern crate hyper;
+///
+/// use gotham::http::response::create_response;
+/// use gotham::pipeline::new_pipeline;
+/// use gotham::pipeline::single::single_pipeline;
+/// use gotham::router::builder::*;
+/// use gotham::router::Router;
+/// use gotham::state::State;
+/// use gotham_cors_middleware::{cors_preflight_handler, CORSMiddleware};
+/// use hyper::{Response, StatusCode};
+///
+/// fn show_user(state: State) -> (State, Response) {
+///     let response = create_response(&state, StatusCode::Ok, None);
+///
+///     (state, response)
+/// }
+///
+/// pub fn router() -> Router {
+///     let (chain, pipeline) = single_pipeline(
+///         new_pipeline()
+///             .add(CORSMiddleware::builder().pass_preflights().build())
+///             .build()
+///     );
+///
+///     build_router(chain, pipeline, |route| {
+///         route.associate("/users/:id", |assoc| {
+///             assoc.get().to(show_user);
+///             assoc.options().to(cors_preflight_handler);
+///         });
+///
+///         route.options("/").to(cors_preflight_handler);
+///         route.options("/*").to(cors_preflight_handler);
+///     })
+/// }
+/// # fn main() { drop(router()); }
+/// ```
+pub fn cors_preflight_handler(mut state: State) -> (State, Response) {
+    let response = match state.try_take::<PreflightPolicy>() {
+        Some(PreflightPolicy(middleware, check)) => {
+            let mut response = middleware.preflight_response(&state, &check);
+            middleware.record_timing(&mut state, &check);
+
+            // The headers are all set, so the middleware leaves
+            // the response as it is on the way out
+            response.headers_mut().set_raw(NO_CORS_HEADER, "1");
+            response
+        }
+        None => Response::new().with_status(StatusCode::NoContent),
+    };
+
+    (state, response)
+}
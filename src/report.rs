@@ -0,0 +1,409 @@
+//! Machine readable reporting of cross-origin requests that
+//! the middleware declined to authorise.
+//!
+//! Unlike logging, a reporter receives structured events which
+//! can be collected and analysed later on. Blocked requests are
+//! only logged when a block log window is set, and a BlockLogLimiter
+//! then logs each origin at most once within it.
+//!
+//! The time spent deciding on origins, and the headers the
+//! middleware changed, can also be recorded.
+use hyper::header::Headers;
+use hyper::Method;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use same_arc;
+
+/// Number of origins tracked at once. Tracking a new origin
+/// beyond this forgets the one tracked the longest.
+const LIMITER_CAPACITY: usize = 1024;
+
+/// Details of a single request whose origin was not
+/// allowed by the middleware.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockEvent {
+    /// The value of the Origin header sent with the request.
+    pub origin: String,
+    /// The method of the blocked request.
+    pub method: Method,
+    /// The path of the blocked request.
+    pub path: String,
+    /// The time at which the request was blocked.
+    pub timestamp: SystemTime,
+}
+
+/// A user supplied sink that receives a BlockEvent every
+/// time the middleware blocks a request.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham_cors_middleware;
+///
+/// use gotham_cors_middleware::{BlockReporter, CORSMiddleware};
+/// use std::sync::mpsc::channel;
+///
+/// fn main() {
+///     let (sender, receiver) = channel();
+///
+///     let middleware = CORSMiddleware::builder()
+///         .origin(Some("http://www.example.com".to_string()))
+///         .block_reporter(BlockReporter::channel(sender))
+///         .build();
+///
+///     // Events can later be read from the receiver
+///     assert!(receiver.try_recv().is_err());
+/// #   drop(middleware);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BlockReporter {
+    sink: Arc<BlockSink>,
+}
+
+type BlockSink = dyn Fn(BlockEvent) + Send + Sync + RefUnwindSafe;
+
+impl BlockReporter {
+    /// Creates a BlockReporter which calls the given
+    /// function with every BlockEvent.
+    pub fn new<F>(sink: F) -> BlockReporter
+    where
+        F: Fn(BlockEvent) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        BlockReporter {
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Creates a BlockReporter which sends every BlockEvent
+    /// down the given channel.
+    ///
+    /// Events are silently dropped once the receiving end
+    /// of the channel has hung up.
+    pub fn channel(sender: Sender<BlockEvent>) -> BlockReporter {
+        let sender = Mutex::new(sender);
+
+        BlockReporter::new(move |event| {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(event);
+            }
+        })
+    }
+
+    pub(crate) fn report(&self, event: BlockEvent) {
+        (self.sink)(event)
+    }
+}
+
+impl fmt::Debug for BlockReporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BlockReporter { .. }")
+    }
+}
+
+impl PartialEq for BlockReporter {
+    fn eq(&self, other: &BlockReporter) -> bool {
+        same_arc(&self.sink, &other.sink)
+    }
+}
+
+/// How long the middleware took to decide whether to allow
+/// the origin of a request, put into the State once the
+/// response is ready when recording it is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, StateData)]
+pub struct CorsDecisionTiming {
+    /// The time taken by the origin decision.
+    pub elapsed: Duration,
+}
+
+/// The headers the middleware changed on a single response,
+/// keyed by header name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeaderDiff {
+    /// Headers the response did not have before, with their values.
+    pub added: BTreeMap<String, String>,
+    /// Headers whose value changed, with their old and new values.
+    pub modified: BTreeMap<String, (String, String)>,
+    /// Headers taken off the response, with their old values.
+    pub removed: BTreeMap<String, String>,
+}
+
+impl HeaderDiff {
+    pub(crate) fn between(before: &Headers, after: &Headers) -> HeaderDiff {
+        let mut diff = HeaderDiff::default();
+
+        for header in after.iter() {
+            let name = header.name().to_string();
+            let value = header.value_string();
+
+            let old = before
+                .iter()
+                .find(|old| old.name().eq_ignore_ascii_case(&name))
+                .map(|old| old.value_string());
+
+            match old {
+                None => {
+                    diff.added.insert(name, value);
+                }
+                Some(ref old) if *old == value => {}
+                Some(old) => {
+                    diff.modified.insert(name, (old, value));
+                }
+            }
+        }
+
+        for header in before.iter() {
+            if after.get_raw(header.name()).is_none() {
+                diff.removed
+                    .insert(header.name().to_string(), header.value_string());
+            }
+        }
+
+        diff
+    }
+}
+
+/// A user supplied callback that receives the HeaderDiff of
+/// every response the middleware adds CORS headers to, for
+/// debugging exactly what it changed.
+#[derive(Clone)]
+pub struct HeaderDiffRecorder {
+    callback: Arc<DiffCallback>,
+}
+
+type DiffCallback = dyn Fn(HeaderDiff) + Send + Sync + RefUnwindSafe;
+
+impl HeaderDiffRecorder {
+    /// Creates a HeaderDiffRecorder which calls the given
+    /// function with every HeaderDiff.
+    pub fn new<F>(callback: F) -> HeaderDiffRecorder
+    where
+        F: Fn(HeaderDiff) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        HeaderDiffRecorder {
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub(crate) fn record(&self, diff: HeaderDiff) {
+        (self.callback)(diff)
+    }
+}
+
+impl fmt::Debug for HeaderDiffRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("HeaderDiffRecorder { .. }")
+    }
+}
+
+impl PartialEq for HeaderDiffRecorder {
+    fn eq(&self, other: &HeaderDiffRecorder) -> bool {
+        same_arc(&self.callback, &other.callback)
+    }
+}
+
+/// Limits block logging to once per origin within a window
+/// of time, counting the blocks left out in between.
+#[derive(Clone)]
+pub(crate) struct BlockLogLimiter {
+    window: Duration,
+    seen: Arc<Mutex<SeenOrigins>>,
+}
+
+/// When each tracked origin was last logged and how many of its
+/// blocks were left out since, along with the order the origins
+/// were first tracked in.
+#[derive(Default)]
+struct SeenOrigins {
+    entries: HashMap<String, (Instant, u64)>,
+    order: VecDeque<String>,
+}
+
+impl BlockLogLimiter {
+    pub(crate) fn new(window: Duration) -> BlockLogLimiter {
+        BlockLogLimiter {
+            window,
+            seen: Arc::new(Mutex::new(SeenOrigins::default())),
+        }
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a block from the origin at the given instant.
+    ///
+    /// Returns the number of blocks suppressed since the origin
+    /// was last logged when this one should be logged, or None
+    /// when it falls within the window and is suppressed.
+    pub(crate) fn admit(&self, origin: &str, now: Instant) -> Option<u64> {
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(_) => return Some(0),
+        };
+
+        if let Some(entry) = seen.entries.get_mut(origin) {
+            if now.duration_since(entry.0) < self.window {
+                entry.1 += 1;
+                return None;
+            }
+
+            let suppressed = entry.1;
+            *entry = (now, 0);
+            return Some(suppressed);
+        }
+
+        if seen.order.len() >= LIMITER_CAPACITY {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.entries.remove(&oldest);
+            }
+        }
+
+        seen.order.push_back(origin.to_string());
+        seen.entries.insert(origin.to_string(), (now, 0));
+        Some(0)
+    }
+}
+
+impl fmt::Debug for BlockLogLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockLogLimiter")
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl PartialEq for BlockLogLimiter {
+    fn eq(&self, other: &BlockLogLimiter) -> bool {
+        self.window == other.window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_header_diff_between() {
+        let mut before = Headers::new();
+        before.set_raw("Vary", "Cookie");
+        before.set_raw("X-Removed", "1");
+        before.set_raw("X-Kept", "same");
+
+        let mut after = Headers::new();
+        after.set_raw("Vary", "Cookie, Origin");
+        after.set_raw("X-Kept", "same");
+        after.set_raw("Access-Control-Allow-Origin", "*");
+
+        let diff = HeaderDiff::between(&before, &after);
+
+        assert_eq!(
+            diff.added.into_iter().collect::<Vec<_>>(),
+            vec![("Access-Control-Allow-Origin".to_string(), "*".to_string())]
+        );
+        assert_eq!(
+            diff.modified.into_iter().collect::<Vec<_>>(),
+            vec![(
+                "Vary".to_string(),
+                ("Cookie".to_string(), "Cookie, Origin".to_string())
+            )]
+        );
+        assert_eq!(
+            diff.removed.into_iter().collect::<Vec<_>>(),
+            vec![("X-Removed".to_string(), "1".to_string())]
+        );
+    }
+
+    fn event() -> BlockEvent {
+        BlockEvent {
+            origin: "http://www.example.com".to_string(),
+            method: Method::Get,
+            path: "/".to_string(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_channel_reporter_sends_events() {
+        let (sender, receiver) = channel();
+        let reporter = BlockReporter::channel(sender);
+
+        let sent = event();
+        reporter.report(sent.clone());
+
+        assert_eq!(receiver.try_recv().unwrap(), sent);
+    }
+
+    #[test]
+    fn test_channel_reporter_ignores_hung_up_receiver() {
+        let (sender, receiver) = channel();
+        let reporter = BlockReporter::channel(sender);
+
+        drop(receiver);
+
+        reporter.report(event());
+    }
+
+    #[test]
+    fn test_limiter_logs_once_per_window() {
+        let limiter = BlockLogLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(limiter.admit("http://www.evil.com", start), Some(0));
+        assert_eq!(limiter.admit("http://www.evil.com", start), None);
+        assert_eq!(
+            limiter.admit("http://www.evil.com", start + Duration::from_secs(59)),
+            None
+        );
+
+        // Other origins are tracked separately
+        assert_eq!(limiter.admit("http://www.other.com", start), Some(0));
+
+        assert_eq!(
+            limiter.admit("http://www.evil.com", start + Duration::from_secs(60)),
+            Some(2)
+        );
+        assert_eq!(
+            limiter.admit("http://www.evil.com", start + Duration::from_secs(61)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_limiter_caps_tracked_origins() {
+        let limiter = BlockLogLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        for i in 0..LIMITER_CAPACITY + 1 {
+            limiter.admit(&format!("http://{}.example.com", i), start);
+        }
+
+        {
+            let seen = limiter.seen.lock().unwrap();
+
+            assert_eq!(seen.entries.len(), LIMITER_CAPACITY);
+            assert_eq!(seen.order.len(), LIMITER_CAPACITY);
+        }
+
+        // The first origin was forgotten, the latest is still tracked
+        assert_eq!(limiter.admit("http://0.example.com", start), Some(0));
+        assert_eq!(
+            limiter.admit(&format!("http://{}.example.com", LIMITER_CAPACITY), start),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reporter_equality() {
+        let reporter = BlockReporter::new(|_| {});
+
+        assert_eq!(reporter, reporter.clone());
+        assert_ne!(reporter, BlockReporter::new(|_| {}));
+    }
+}
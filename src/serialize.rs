@@ -0,0 +1,255 @@
+//! Serde support for reading a CORSMiddleware from a
+//! configuration file.
+//!
+//! Only available with the "serde" feature enabled.
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+use std::fmt;
+
+use config::CORSConfig;
+use max_age::MaxAge;
+use CORSMiddleware;
+
+/// Reads and writes optional methods by their names, such as
+/// "GET", for use with `#[serde(with = "...")]`.
+pub(crate) mod methods {
+    use hyper::Method;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    pub(crate) fn serialize<S>(
+        methods: &Option<Vec<Method>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        methods
+            .as_ref()
+            .map(|methods| methods.iter().map(AsRef::as_ref).collect::<Vec<&str>>())
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<Method>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<Vec<String>>::deserialize(deserializer)? {
+            Some(names) => names
+                .iter()
+                .map(|name| name.parse().map_err(de::Error::custom))
+                .collect::<Result<_, _>>()
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Seconds are written as a number, and the other values by
+/// the same names the config string uses.
+impl Serialize for MaxAge {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            MaxAge::Seconds(seconds) => serializer.serialize_u32(seconds),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaxAge {
+    fn deserialize<D>(deserializer: D) -> Result<MaxAge, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(MaxAgeVisitor)
+    }
+}
+
+struct MaxAgeVisitor;
+
+impl<'de> Visitor<'de> for MaxAgeVisitor {
+    type Value = MaxAge;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number of seconds, \"no_cache\" or \"browser_default\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, seconds: u64) -> Result<MaxAge, E> {
+        if seconds > u64::from(u32::MAX) {
+            return Err(E::custom("max age out of range"));
+        }
+
+        Ok(MaxAge::Seconds(seconds as u32))
+    }
+
+    fn visit_i64<E: de::Error>(self, seconds: i64) -> Result<MaxAge, E> {
+        if seconds < 0 {
+            return Err(E::custom("max age must not be negative"));
+        }
+
+        self.visit_u64(seconds as u64)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<MaxAge, E> {
+        value
+            .parse()
+            .map_err(|()| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+/// Writes the options a CORSConfig holds.
+///
+/// Serializing fails when any other option, such as a denylist,
+/// a resolver or a path policy, differs from its default, as it
+/// would be lost when read back. Use to_config_string() for
+/// those.
+impl Serialize for CORSMiddleware {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let config = CORSConfig::from(self);
+
+        if CORSMiddleware::from_config(config.clone()).as_ref() != Ok(self) {
+            return Err(ser::Error::custom(
+                "the middleware has options a CORSConfig cannot hold",
+            ));
+        }
+
+        config.serialize(serializer)
+    }
+}
+
+/// Reads a CORSConfig and builds the middleware from it, failing
+/// when one of its origins is not valid.
+///
+/// Example of use:
+/// ```rust
+/// extern crate gotham_cors_middleware;
+/// extern crate serde_json;
+///
+/// use gotham_cors_middleware::CORSMiddleware;
+///
+/// fn main() {
+///     let middleware: CORSMiddleware = serde_json::from_str(
+///         r#"{
+///             "origins": ["https://app.example.com"],
+///             "methods": ["GET", "POST"],
+///             "max_age": 600,
+///             "credentials": false
+///         }"#,
+///     ).unwrap();
+/// #   drop(middleware);
+/// }
+/// ```
+impl<'de> Deserialize<'de> for CORSMiddleware {
+    fn deserialize<D>(deserializer: D) -> Result<CORSMiddleware, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let config = CORSConfig::deserialize(deserializer)?;
+
+        CORSMiddleware::from_config(config).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Method;
+    use serde_json;
+
+    #[test]
+    fn test_json_round_trip() {
+        let middleware = CORSMiddleware::from_config(CORSConfig {
+            origins: Some(vec![
+                "https://app.example.com".to_string(),
+                "https://admin.example.com".to_string(),
+            ]),
+            methods: Some(vec![
+                Method::Get,
+                Method::Post,
+                Method::Extension("PURGE".to_string()),
+            ]),
+            credentials: Some(false),
+            max_age: Some(MaxAge::Seconds(600)),
+            ..CORSConfig::default()
+        })
+        .unwrap();
+
+        let json = serde_json::to_string(&middleware).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<CORSMiddleware>(&json).unwrap(),
+            middleware
+        );
+    }
+
+    #[test]
+    fn test_options_beyond_config_not_serialized() {
+        let middleware = CORSMiddleware::builder()
+            .denied_origins(vec!["https://evil.example.com"])
+            .unwrap()
+            .build();
+
+        assert!(serde_json::to_string(&middleware).is_err());
+
+        // Whichever strictness is not the default for this build
+        let middleware = CORSMiddleware::builder()
+            .allowed_origins(vec!["https://app.example.com"])
+            .unwrap()
+            .strict_allowlist(!cfg!(feature = "production"))
+            .build();
+
+        assert!(serde_json::to_string(&middleware).is_err());
+    }
+
+    #[test]
+    fn test_methods_as_strings() {
+        let middleware = CORSMiddleware::builder()
+            .methods(vec![Method::Get, Method::Options])
+            .build();
+
+        let json = serde_json::to_value(&middleware).unwrap();
+
+        assert_eq!(
+            json["methods"],
+            serde_json::to_value(vec!["GET", "OPTIONS"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_max_age_values() {
+        for &max_age in &[
+            MaxAge::Seconds(600),
+            MaxAge::NoCache,
+            MaxAge::BrowserDefault,
+        ] {
+            let json = serde_json::to_string(&max_age).unwrap();
+
+            assert_eq!(serde_json::from_str::<MaxAge>(&json).unwrap(), max_age);
+        }
+
+        assert_eq!(serde_json::to_string(&MaxAge::Seconds(600)).unwrap(), "600");
+        assert!(serde_json::from_str::<MaxAge>("-1").is_err());
+        assert!(serde_json::from_str::<MaxAge>("\"soon\"").is_err());
+    }
+
+    #[test]
+    fn test_missing_fields_default() {
+        let middleware: CORSMiddleware = serde_json::from_str("{}").unwrap();
+
+        assert_eq!(middleware, CORSMiddleware::default());
+    }
+
+    #[test]
+    fn test_invalid_origin() {
+        let result = serde_json::from_str::<CORSMiddleware>(r#"{"origins": ["app.example.com"]}"#);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! A configuration shared with the rest of the app, which can
+//! be swapped out at runtime without restarting the server.
+use arc_swap::ArcSwap;
+use std::fmt;
+use std::sync::Arc;
+
+use config::CORSConfig;
+use error::CorsError;
+use CORSMiddleware;
+
+/// A shared configuration along with the CORSMiddleware
+/// built from it.
+struct Compiled {
+    config: Arc<CORSConfig>,
+    middleware: Arc<CORSMiddleware>,
+}
+
+/// Handle on the shared configuration a CORSMiddleware
+/// reads on every request.
+#[derive(Clone)]
+pub(crate) struct SharedConfig {
+    config: Arc<ArcSwap<CORSConfig>>,
+    compiled: Arc<ArcSwap<Compiled>>,
+}
+
+impl SharedConfig {
+    /// Loads the middleware for the configuration in place for
+    /// the request at hand. It is only built again after a new
+    /// configuration has been stored.
+    pub(crate) fn current(&self) -> Arc<CORSMiddleware> {
+        let config = self.config.load();
+        let compiled = self.compiled.load();
+
+        if Arc::ptr_eq(&config, &compiled.config) {
+            return compiled.middleware.clone();
+        }
+
+        let middleware = match CORSMiddleware::from_config((**config).clone()) {
+            Ok(middleware) => Arc::new(middleware),
+            Err(err) => {
+                warn!("Keeping the previous CORS policy: {}", err);
+                compiled.middleware.clone()
+            }
+        };
+
+        self.compiled.store(Arc::new(Compiled {
+            config: Arc::clone(&config),
+            middleware: middleware.clone(),
+        }));
+
+        middleware
+    }
+}
+
+impl fmt::Debug for SharedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SharedConfig { .. }")
+    }
+}
+
+impl PartialEq for SharedConfig {
+    fn eq(&self, other: &SharedConfig) -> bool {
+        Arc::ptr_eq(&self.config, &other.config)
+    }
+}
+
+impl CORSMiddleware {
+    /// Creates a CORSMiddleware which follows a CORSConfig held
+    /// elsewhere. Storing a new CORSConfig into it changes the
+    /// policy from the next request on, with requests only paying
+    /// for a lock free load.
+    ///
+    /// The middleware is built from each new configuration once,
+    /// as CORSMiddleware::from_config() would build it. A stored
+    /// configuration with an invalid origin is ignored, keeping
+    /// the policy in place before it.
+    ///
+    /// Returns an error naming the first origin of the current
+    /// configuration which is not valid.
+    ///
+    /// Example of use:
+    /// ```rust
+    /// extern crate arc_swap;
+    /// extern crate gotham_cors_middleware;
+    ///
+    /// use arc_swap::ArcSwap;
+    /// use gotham_cors_middleware::{CORSConfig, CORSMiddleware};
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let config = Arc::new(ArcSwap::new(Arc::new(CORSConfig::default())));
+    ///     let middleware = CORSMiddleware::from_shared(config.clone()).unwrap();
+    ///
+    ///     // Later on, from anywhere holding the config
+    ///     config.store(Arc::new(CORSConfig {
+    ///         origins: Some(vec!["https://app.example.com".to_string()]),
+    ///         ..CORSConfig::default()
+    ///     }));
+    /// #   drop(middleware);
+    /// }
+    /// ```
+    pub fn from_shared(config: Arc<ArcSwap<CORSConfig>>) -> Result<CORSMiddleware, CorsError> {
+        let current = config.load_full();
+        let middleware = CORSMiddleware::from_config((*current).clone())?;
+
+        let compiled = Compiled {
+            config: current,
+            middleware: Arc::new(middleware),
+        };
+
+        Ok(CORSMiddleware {
+            shared: Some(SharedConfig {
+                config,
+                compiled: Arc::new(ArcSwap::new(Arc::new(compiled))),
+            }),
+            ..CORSMiddleware::default()
+        })
+    }
+}
@@ -0,0 +1,685 @@
+//! Snapshots of a CORSMiddleware's configuration as plain
+//! text, so it can be kept in a file and reloaded.
+//!
+//! Each line holds one `key = value` pair, with lists being
+//! separated by commas. Blank lines and lines starting with
+//! "#" are ignored.
+use hyper::StatusCode;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use error::CorsError;
+use headers::ActualAllowHeaders;
+use max_age::MaxAge;
+use origin::{ExtensionOrigin, OriginSettings};
+use path::PathPolicy;
+use {CORSMiddleware, CORSMiddlewareBuilder};
+
+impl CORSMiddleware {
+    /// Writes the configuration out as a string which
+    /// from_config_string() reads back into an equal
+    /// CORSMiddleware.
+    ///
+    /// Block reporters, origin resolvers, comparators and hooks
+    /// are functions and preflight bodies may not be text, so
+    /// these are left out and need setting up again after the
+    /// configuration has been read back.
+    pub fn to_config_string(&self) -> String {
+        let mut config = String::new();
+
+        let _ = writeln!(config, "methods = {}", join(self.methods.as_slice()));
+
+        if let Some(ref origin) = self.origin {
+            let _ = writeln!(config, "origin = {}", origin);
+        }
+
+        let _ = writeln!(config, "max_age = {}", self.max_age);
+        let _ = writeln!(config, "allow_headers = {}", self.allow_headers.join(", "));
+        let _ = writeln!(
+            config,
+            "expose_headers = {}",
+            self.expose_headers.join(", ")
+        );
+        let _ = writeln!(
+            config,
+            "canonicalize_allow_headers = {}",
+            self.canonicalize_allow_headers
+        );
+
+        match self.actual_allow_headers {
+            ActualAllowHeaders::SameAsPreflight => {
+                let _ = writeln!(config, "actual_allow_headers = same");
+            }
+            ActualAllowHeaders::Omit => {
+                let _ = writeln!(config, "actual_allow_headers = omit");
+            }
+            ActualAllowHeaders::List(ref list) => {
+                let _ = writeln!(config, "actual_allow_headers = list {}", list.join(", "));
+            }
+        }
+
+        if let Some(limit) = self.max_requested_headers {
+            let _ = writeln!(config, "max_requested_headers = {}", limit);
+        }
+
+        if let Some(limit) = self.max_header_bytes {
+            let _ = writeln!(config, "max_header_bytes = {}", limit);
+        }
+
+        if let Some(version) = self.policy_version {
+            let _ = writeln!(config, "policy_version = {}", version);
+        }
+
+        if let Some(max_age) = self.headers_preflight_max_age {
+            let _ = writeln!(config, "headers_preflight_max_age = {}", max_age);
+        }
+
+        let (matched_extensions, origins): (Vec<_>, Vec<_>) = self
+            .allowed_origins
+            .iter()
+            .partition(|matcher| matcher.extension().is_some());
+
+        let extensions: Vec<String> = matched_extensions
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.allowed_extensions.iter().map(ToString::to_string))
+            .collect();
+
+        let _ = writeln!(config, "allowed_origins = {}", join(&origins));
+        let _ = writeln!(config, "allowed_extensions = {}", join(&extensions));
+
+        #[cfg(feature = "suffix-allowlist")]
+        {
+            if let Some(ref suffixes) = self.allowed_suffixes {
+                let _ = writeln!(
+                    config,
+                    "allowed_suffixes = {}",
+                    suffixes.suffixes().join(", ")
+                );
+            }
+        }
+
+        let _ = writeln!(config, "denied_origins = {}", join(&self.denied_origins));
+        let _ = writeln!(
+            config,
+            "denied_headers = {}",
+            self.denied_headers.join(", ")
+        );
+        let _ = writeln!(config, "strict_allowlist = {}", self.strict_allowlist);
+        let _ = writeln!(
+            config,
+            "allowed_schemes = {}",
+            self.allowed_schemes.join(", ")
+        );
+        let _ = writeln!(
+            config,
+            "empty_origin_as_absent = {}",
+            self.empty_origin_as_absent
+        );
+        let _ = writeln!(config, "allow_null_origin = {}", self.allow_null_origin);
+        let _ = writeln!(
+            config,
+            "echo_safelisted_headers = {}",
+            self.echo_safelisted_headers
+        );
+        let _ = writeln!(
+            config,
+            "mirror_request_headers = {}",
+            self.mirror_request_headers
+        );
+        let _ = writeln!(
+            config,
+            "reflect_request_headers = {}",
+            self.reflect_request_headers
+        );
+        let _ = writeln!(
+            config,
+            "reflect_request_method = {}",
+            self.reflect_request_method
+        );
+        let _ = writeln!(
+            config,
+            "allow_private_network = {}",
+            self.allow_private_network
+        );
+        let _ = writeln!(config, "max_age_on_actual = {}", self.max_age_on_actual);
+        let _ = writeln!(config, "methods_on_actual = {}", self.methods_on_actual);
+        let _ = writeln!(config, "credentials = {}", self.credentials);
+        let _ = writeln!(config, "vary_origin = {}", self.vary_origin);
+        let _ = writeln!(
+            config,
+            "vary_reflected_origin = {}",
+            self.vary_reflected_origin
+        );
+        let _ = writeln!(config, "extra_vary = {}", self.extra_vary.join(", "));
+        let _ = writeln!(
+            config,
+            "disallowed_preflight = {}",
+            self.disallowed_preflight
+        );
+        let _ = writeln!(config, "disallowed_actual = {}", self.disallowed_actual);
+        let _ = writeln!(
+            config,
+            "skip_private_clients = {}",
+            self.skip_private_clients
+        );
+        let _ = writeln!(config, "content_types = {}", join(&self.content_types));
+        let _ = writeln!(
+            config,
+            "require_referer_match = {}",
+            self.require_referer_match
+        );
+        let _ = writeln!(
+            config,
+            "reject_downgraded_origins = {}",
+            self.reject_downgraded_origins
+        );
+        let _ = writeln!(config, "reflect_raw_origin = {}", self.reflect_raw_origin);
+        let _ = writeln!(
+            config,
+            "list_allowed_origins = {}",
+            self.list_allowed_origins
+        );
+        let _ = writeln!(config, "emit_without_origin = {}", self.emit_without_origin);
+        let _ = writeln!(config, "overwrite_existing = {}", self.overwrite_existing);
+        let _ = writeln!(config, "origin_sources = {}", join(&self.origin_sources));
+        let _ = writeln!(config, "trusted_proxies = {}", join(&self.trusted_proxies));
+        let _ = writeln!(
+            config,
+            "record_decision_timing = {}",
+            self.record_decision_timing
+        );
+        let _ = writeln!(
+            config,
+            "preflight_paths = {}",
+            self.preflight_paths.join(", ")
+        );
+        let _ = writeln!(config, "lenient_preflights = {}", self.lenient_preflights);
+        let _ = writeln!(
+            config,
+            "preflight_status = {}",
+            self.preflight_status.as_u16()
+        );
+        let _ = writeln!(
+            config,
+            "reject_unsupported_methods = {}",
+            self.reject_unsupported_methods
+        );
+
+        if let Some(ref limiter) = self.block_log_limiter {
+            let window = limiter.window();
+            let millis = window.as_secs() * 1000 + u64::from(window.subsec_millis());
+
+            let _ = writeln!(config, "block_log_window_ms = {}", millis);
+        }
+
+        for matcher in &self.allowed_origins {
+            let settings = matcher.settings();
+
+            if settings != OriginSettings::default() {
+                let _ = writeln!(
+                    config,
+                    "origin_settings = {} | {} | {}",
+                    matcher,
+                    optional(settings.credentials),
+                    optional(settings.max_age)
+                );
+            }
+        }
+
+        for policy in &self.path_policies {
+            let _ = writeln!(
+                config,
+                "path_policy = {} | {} | {}",
+                policy.template(),
+                join(policy.methods()),
+                policy.headers().join(", ")
+            );
+        }
+
+        config
+    }
+
+    /// Reads a configuration written by to_config_string(),
+    /// starting from the default values for any keys which
+    /// are missing.
+    ///
+    /// Example of use:
+    /// ```rust
+    /// extern crate gotham_cors_middleware;
+    ///
+    /// use gotham_cors_middleware::CORSMiddleware;
+    ///
+    /// fn main() {
+    ///     let middleware = CORSMiddleware::from_config_string(
+    ///         "methods = GET, POST\n\
+    ///          allowed_origins = https://app.example.com\n\
+    ///          max_age = 600",
+    ///     ).unwrap();
+    ///
+    ///     let reloaded =
+    ///         CORSMiddleware::from_config_string(&middleware.to_config_string()).unwrap();
+    ///
+    ///     assert_eq!(reloaded, middleware);
+    /// }
+    /// ```
+    pub fn from_config_string(config: &str) -> Result<CORSMiddleware, CorsError> {
+        let mut builder = CORSMiddlewareBuilder::new();
+        let mut path_policies = Vec::new();
+        let mut origin_settings = Vec::new();
+
+        for (idx, line) in config.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason| CorsError::InvalidConfig {
+                line: idx + 1,
+                reason,
+            };
+
+            let split = match line.find('=') {
+                Some(split) => split,
+                None => return Err(invalid("expected key = value")),
+            };
+
+            let key = line[..split].trim();
+            let value = line[split + 1..].trim();
+
+            builder = match key {
+                "methods" => {
+                    builder.methods(parse_list(value).map_err(|_| invalid("invalid method"))?)
+                }
+                "origin" => builder.origin(Some(value.to_string())),
+                "max_age" => builder.max_age_policy(
+                    value
+                        .parse::<MaxAge>()
+                        .map_err(|_| invalid("invalid number"))?,
+                ),
+                "allow_headers" => builder.allow_headers(split_list(value)),
+                "expose_headers" => builder.expose_headers(split_list(value)),
+                "canonicalize_allow_headers" => builder.canonicalize_allow_headers(
+                    value.parse().map_err(|_| invalid("invalid bool"))?,
+                ),
+                "actual_allow_headers" => builder.actual_allow_headers(match value {
+                    "same" => ActualAllowHeaders::SameAsPreflight,
+                    "omit" => ActualAllowHeaders::Omit,
+                    list if list.starts_with("list") => ActualAllowHeaders::List(
+                        split_list(&list[4..])
+                            .into_iter()
+                            .map(str::to_string)
+                            .collect(),
+                    ),
+                    _ => return Err(invalid("expected same, omit or list")),
+                }),
+                "max_requested_headers" => builder
+                    .max_requested_headers(value.parse().map_err(|_| invalid("invalid number"))?),
+                "max_header_bytes" => {
+                    builder.max_header_bytes(value.parse().map_err(|_| invalid("invalid number"))?)
+                }
+                "policy_version" => {
+                    builder.policy_version(value.parse().map_err(|_| invalid("invalid number"))?)
+                }
+                "headers_preflight_max_age" => builder.headers_preflight_max_age(
+                    value.parse().map_err(|_| invalid("invalid number"))?,
+                ),
+                "allowed_origins" => builder.allowed_origins(split_list(value))?,
+                "allowed_extensions" => {
+                    let mut extensions = Vec::new();
+
+                    for extension in split_list(value) {
+                        match extension.find("://") {
+                            Some(idx) => extensions.push(ExtensionOrigin::new(
+                                &extension[..idx],
+                                &extension[idx + 3..],
+                            )),
+                            None => return Err(invalid("invalid extension origin")),
+                        }
+                    }
+
+                    builder.allowed_extensions(extensions)
+                }
+                #[cfg(feature = "suffix-allowlist")]
+                "allowed_suffixes" => builder.allowed_suffixes(split_list(value)),
+                "denied_origins" => builder.denied_origins(split_list(value))?,
+                "denied_headers" => builder.denied_headers(split_list(value)),
+                "strict_allowlist" => {
+                    builder.strict_allowlist(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "allowed_schemes" => builder.allowed_schemes(split_list(value)),
+                "empty_origin_as_absent" => builder
+                    .empty_origin_as_absent(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "allow_null_origin" => {
+                    builder.allow_null_origin(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "echo_safelisted_headers" => builder
+                    .echo_safelisted_headers(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "mirror_request_headers" => builder
+                    .mirror_request_headers(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "reflect_request_headers" => builder
+                    .reflect_request_headers(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "reflect_request_method" => builder
+                    .reflect_request_method(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "allow_private_network" => builder
+                    .allow_private_network(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "max_age_on_actual" => {
+                    builder.max_age_on_actual(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "methods_on_actual" => {
+                    builder.methods_on_actual(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "credentials" => {
+                    builder.allow_credentials(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "vary_origin" => {
+                    builder.vary_origin(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "vary_reflected_origin" => builder
+                    .vary_reflected_origin(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "extra_vary" => builder.extra_vary(split_list(value)),
+                "disallowed_preflight" => builder.disallowed_preflight(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("invalid disallowed response"))?,
+                ),
+                "disallowed_actual" => builder.disallowed_actual(
+                    value
+                        .parse()
+                        .map_err(|_| invalid("invalid disallowed response"))?,
+                ),
+                "skip_private_clients" => builder
+                    .skip_private_clients(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "content_types" => builder
+                    .content_types(parse_list(value).map_err(|_| invalid("invalid content type"))?),
+                "record_decision_timing" => builder
+                    .record_decision_timing(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "origin_sources" => builder.origin_sources(parse_list(value)?),
+                "trusted_proxies" => builder
+                    .trusted_proxies(parse_list(value).map_err(|_| invalid("invalid IP address"))?),
+                "reject_downgraded_origins" => builder
+                    .reject_downgraded_origins(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "list_allowed_origins" => builder
+                    .list_allowed_origins(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "emit_without_origin" => {
+                    builder.emit_without_origin(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "overwrite_existing" => {
+                    builder.overwrite_existing(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "reflect_raw_origin" => {
+                    builder.reflect_raw_origin(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "require_referer_match" => builder
+                    .require_referer_match(value.parse().map_err(|_| invalid("invalid bool"))?),
+                "preflight_paths" => builder.preflight_paths(split_list(value)),
+                "reject_unsupported_methods" => builder.reject_unsupported_methods(
+                    value.parse().map_err(|_| invalid("invalid bool"))?,
+                ),
+                "lenient_preflights" => {
+                    builder.lenient_preflights(value.parse().map_err(|_| invalid("invalid bool"))?)
+                }
+                "preflight_status" => builder.preflight_status(
+                    value
+                        .parse()
+                        .ok()
+                        .and_then(|code| StatusCode::try_from(code).ok())
+                        .ok_or_else(|| invalid("invalid status code"))?,
+                ),
+                "block_log_window_ms" => builder.block_log_window(Duration::from_millis(
+                    value.parse().map_err(|_| invalid("invalid number"))?,
+                )),
+                "path_policy" => {
+                    let parts: Vec<&str> = value.split('|').map(str::trim).collect();
+
+                    if parts.len() != 3 {
+                        return Err(invalid("expected template | methods | headers"));
+                    }
+
+                    path_policies.push(PathPolicy::new(
+                        parts[0],
+                        parse_list(parts[1]).map_err(|_| invalid("invalid method"))?,
+                        split_list(parts[2])
+                            .into_iter()
+                            .map(str::to_string)
+                            .collect(),
+                    ));
+
+                    builder
+                }
+                "origin_settings" => {
+                    let parts: Vec<&str> = value.split('|').map(str::trim).collect();
+
+                    if parts.len() != 3 {
+                        return Err(invalid("expected origin | credentials | max_age"));
+                    }
+
+                    origin_settings.push((
+                        parts[0].to_string(),
+                        OriginSettings {
+                            credentials: parse_optional(parts[1])
+                                .map_err(|_| invalid("invalid bool"))?,
+                            max_age: parse_optional(parts[2])
+                                .map_err(|_| invalid("invalid number"))?,
+                        },
+                    ));
+
+                    builder
+                }
+                _ => return Err(invalid("unknown key")),
+            };
+        }
+
+        let mut middleware = builder.path_policies(path_policies).build();
+
+        for (origin, settings) in origin_settings {
+            for matcher in &mut middleware.allowed_origins {
+                if matcher.to_string() == origin {
+                    *matcher = matcher.clone().with_settings(settings);
+                }
+            }
+        }
+
+        Ok(middleware)
+    }
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes an optional value, with "-" standing for None.
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+fn parse_optional<T: FromStr>(value: &str) -> Result<Option<T>, T::Err> {
+    match value {
+        "-" => Ok(None),
+        value => value.parse().map(Some),
+    }
+}
+
+fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn parse_list<T: FromStr>(value: &str) -> Result<Vec<T>, T::Err> {
+    split_list(value).into_iter().map(str::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use disallowed::DisallowedResponse;
+    use hyper::Method;
+    use mime;
+    use origin::{OriginMatcher, OriginSource};
+
+    #[test]
+    fn test_default_round_trip() {
+        let middleware = CORSMiddleware::default();
+
+        assert_eq!(
+            CORSMiddleware::from_config_string(&middleware.to_config_string()),
+            Ok(middleware)
+        );
+    }
+
+    #[test]
+    fn test_full_round_trip() {
+        let middleware = CORSMiddleware::builder()
+            .methods(vec![
+                Method::Get,
+                Method::Post,
+                Method::Extension("PURGE".to_string()),
+            ])
+            .origin(Some("https://www.example.com".to_string()))
+            .max_age(1000)
+            .allow_headers(vec!["Content-Type", "X-Api-Key"])
+            .expose_headers(vec!["X-Total-Count"])
+            .actual_allow_headers(ActualAllowHeaders::List(vec!["X-Legacy".to_string()]))
+            .canonicalize_allow_headers(true)
+            .max_header_bytes(2048)
+            .max_requested_headers(32)
+            .headers_preflight_max_age(60)
+            .policy_version(7)
+            .allowed_origins(vec!["https://app.example.com", "http://localhost:3000"])
+            .unwrap()
+            .allowed_schemes(vec!["https", "http"])
+            .allowed_extensions(vec![ExtensionOrigin::chrome("abcdefghijklmnop")])
+            .empty_origin_as_absent(true)
+            .allow_null_origin(true)
+            .echo_safelisted_headers(true)
+            .mirror_request_headers(true)
+            .reflect_request_headers(true)
+            .reflect_request_method(true)
+            .allow_private_network(true)
+            .max_age_on_actual(true)
+            .methods_on_actual(true)
+            .allow_credentials(false)
+            .strict_allowlist(true)
+            .denied_origins(vec!["https://evil.example.com"])
+            .unwrap()
+            .denied_headers(vec!["X-Debug"])
+            .vary_origin(true)
+            .vary_reflected_origin(false)
+            .extra_vary(vec!["Accept-Encoding"])
+            .disallowed_preflight(DisallowedResponse::Forbidden)
+            .disallowed_actual(DisallowedResponse::OmitHeaders)
+            .skip_private_clients(true)
+            .content_types(vec![mime::APPLICATION_JSON])
+            .require_referer_match(true)
+            .reject_downgraded_origins(true)
+            .reflect_raw_origin(true)
+            .list_allowed_origins(true)
+            .emit_without_origin(true)
+            .overwrite_existing(false)
+            .record_decision_timing(true)
+            .origin_sources(vec![
+                OriginSource::Header("Origin".to_string()),
+                OriginSource::Header("X-Forwarded-Origin".to_string()),
+                OriginSource::Forwarded,
+            ])
+            .trusted_proxies(vec!["10.0.0.1".parse().unwrap(), "::1".parse().unwrap()])
+            .preflight_paths(vec!["/app/*", "/login"])
+            .lenient_preflights(true)
+            .preflight_status(StatusCode::Ok)
+            .reject_unsupported_methods(true)
+            .block_log_window(Duration::from_millis(1500))
+            .path_policies(vec![
+                PathPolicy::new(
+                    "/users/{id}",
+                    vec![Method::Get, Method::Delete],
+                    vec!["X-Api-Key".to_string()],
+                ),
+                PathPolicy::new("/health", vec![Method::Get], vec![]),
+            ])
+            .build();
+
+        let config = middleware.to_config_string();
+
+        assert_eq!(CORSMiddleware::from_config_string(&config), Ok(middleware));
+    }
+
+    #[test]
+    fn test_actual_allow_headers_round_trip() {
+        for headers in &[
+            ActualAllowHeaders::SameAsPreflight,
+            ActualAllowHeaders::Omit,
+            ActualAllowHeaders::List(vec![]),
+        ] {
+            let middleware = CORSMiddleware::builder()
+                .actual_allow_headers(headers.clone())
+                .build();
+
+            assert_eq!(
+                CORSMiddleware::from_config_string(&middleware.to_config_string()),
+                Ok(middleware)
+            );
+        }
+    }
+
+    #[cfg(feature = "suffix-allowlist")]
+    #[test]
+    fn test_allowed_suffixes_round_trip() {
+        let middleware = CORSMiddleware::builder()
+            .allowed_suffixes(vec!["example.com", "example.org"])
+            .build();
+
+        assert_eq!(
+            CORSMiddleware::from_config_string(&middleware.to_config_string()),
+            Ok(middleware)
+        );
+    }
+
+    #[test]
+    fn test_origin_settings_round_trip() {
+        let matcher: OriginMatcher = "https://app.example.com".parse().unwrap();
+
+        let middleware = CORSMiddleware::builder()
+            .allowed_matchers(vec![
+                matcher.with_settings(OriginSettings {
+                    credentials: Some(false),
+                    max_age: None,
+                }),
+                "https://admin.example.com".parse().unwrap(),
+            ])
+            .build();
+
+        assert_eq!(
+            CORSMiddleware::from_config_string(&middleware.to_config_string()),
+            Ok(middleware)
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert_eq!(
+            CORSMiddleware::from_config_string("# comment\n\nmax_age = soon"),
+            Err(CorsError::InvalidConfig {
+                line: 3,
+                reason: "invalid number",
+            })
+        );
+        assert_eq!(
+            CORSMiddleware::from_config_string("colour = blue"),
+            Err(CorsError::InvalidConfig {
+                line: 1,
+                reason: "unknown key",
+            })
+        );
+        assert!(CORSMiddleware::from_config_string("allowed_origins = example.com").is_err());
+    }
+}
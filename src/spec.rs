@@ -0,0 +1,322 @@
+//! Checks of a CORSMiddleware against the rules the Fetch
+//! standard places on CORS responses.
+use std::fmt;
+
+use headers::ActualAllowHeaders;
+use CORSMiddleware;
+
+/// Request headers which scripts may not set, so allowing
+/// them in a response has no effect.
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "Accept-Charset",
+    "Accept-Encoding",
+    "Access-Control-Request-Headers",
+    "Access-Control-Request-Method",
+    "Connection",
+    "Content-Length",
+    "Cookie",
+    "Cookie2",
+    "Date",
+    "DNT",
+    "Expect",
+    "Host",
+    "Keep-Alive",
+    "Origin",
+    "Referer",
+    "Set-Cookie",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+    "Via",
+];
+
+/// Prefixes of request headers which scripts may not set.
+const FORBIDDEN_PREFIXES: &[&str] = &["Proxy-", "Sec-"];
+
+/// Response headers which scripts may never read, so exposing
+/// them has no effect.
+const FORBIDDEN_RESPONSE_HEADERS: &[&str] = &["Set-Cookie", "Set-Cookie2"];
+
+/// A way in which a CORSMiddleware breaks the rules of the
+/// Fetch standard, causing browsers to reject its responses
+/// or ignore part of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpecViolation {
+    /// A "*" origin is sent along with credentials, which
+    /// browsers refuse.
+    WildcardOriginWithCredentials,
+    /// A "*" allowed header is sent along with credentials, in
+    /// which case browsers read it as a header named "*".
+    WildcardHeadersWithCredentials,
+    /// A "*" allowed method is sent along with credentials, in
+    /// which case browsers read it as a method named "*".
+    WildcardMethodsWithCredentials,
+    /// A "*" exposed header is sent along with credentials, in
+    /// which case browsers read it as a header named "*".
+    WildcardExposeWithCredentials,
+    /// A header scripts are forbidden from setting is allowed.
+    ForbiddenHeader(String),
+    /// A response header scripts are forbidden from reading is
+    /// exposed.
+    ForbiddenExposedHeader(String),
+}
+
+impl fmt::Display for SpecViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpecViolation::WildcardOriginWithCredentials => {
+                f.write_str("a wildcard origin cannot be used with credentials")
+            }
+            SpecViolation::WildcardHeadersWithCredentials => {
+                f.write_str("wildcard allowed headers cannot be used with credentials")
+            }
+            SpecViolation::WildcardMethodsWithCredentials => {
+                f.write_str("wildcard allowed methods cannot be used with credentials")
+            }
+            SpecViolation::WildcardExposeWithCredentials => {
+                f.write_str("wildcard exposed headers cannot be used with credentials")
+            }
+            SpecViolation::ForbiddenHeader(ref header) => {
+                write!(f, "{} is a forbidden header and cannot be allowed", header)
+            }
+            SpecViolation::ForbiddenExposedHeader(ref header) => {
+                write!(
+                    f,
+                    "{} is a forbidden response header and cannot be exposed",
+                    header
+                )
+            }
+        }
+    }
+}
+
+impl CORSMiddleware {
+    /// Checks the configuration against the rules of the Fetch
+    /// standard, returning every violation found.
+    ///
+    /// Credentials count as sent when they are on for the
+    /// middleware or for any entry on the allowlist.
+    pub fn validate_spec_compliance(&self) -> Result<(), Vec<SpecViolation>> {
+        let mut violations = Vec::new();
+
+        let credentials = self.credentials
+            || self
+                .allowed_origins
+                .iter()
+                .any(|matcher| matcher.settings().credentials == Some(true));
+
+        if credentials && self.origin.as_deref() == Some("*") {
+            violations.push(SpecViolation::WildcardOriginWithCredentials);
+        }
+
+        let mut allowed_headers: Vec<&String> = self.allow_headers.iter().collect();
+
+        for policy in &self.path_policies {
+            allowed_headers.extend(policy.headers());
+        }
+
+        if let ActualAllowHeaders::List(ref list) = self.actual_allow_headers {
+            allowed_headers.extend(list);
+        }
+
+        if credentials && allowed_headers.iter().any(|header| header.trim() == "*") {
+            violations.push(SpecViolation::WildcardHeadersWithCredentials);
+        }
+
+        let mut methods = self.methods.as_slice().iter().chain(
+            self.path_policies
+                .iter()
+                .flat_map(|policy| policy.methods()),
+        );
+
+        if credentials && methods.any(|method| method.as_ref() == "*") {
+            violations.push(SpecViolation::WildcardMethodsWithCredentials);
+        }
+
+        if credentials
+            && self
+                .expose_headers
+                .iter()
+                .any(|header| header.trim() == "*")
+        {
+            violations.push(SpecViolation::WildcardExposeWithCredentials);
+        }
+
+        for header in allowed_headers {
+            let header = header.trim();
+
+            if is_forbidden_header(header)
+                && !violations.contains(&SpecViolation::ForbiddenHeader(header.to_string()))
+            {
+                violations.push(SpecViolation::ForbiddenHeader(header.to_string()));
+            }
+        }
+
+        for header in &self.expose_headers {
+            let header = header.trim();
+            let violation = SpecViolation::ForbiddenExposedHeader(header.to_string());
+
+            if is_forbidden_response_header(header) && !violations.contains(&violation) {
+                violations.push(violation);
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn is_forbidden_header(header: &str) -> bool {
+    FORBIDDEN_HEADERS
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(header))
+        || FORBIDDEN_PREFIXES.iter().any(|prefix| {
+            header
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        })
+}
+
+fn is_forbidden_response_header(header: &str) -> bool {
+    FORBIDDEN_RESPONSE_HEADERS
+        .iter()
+        .any(|forbidden| forbidden.eq_ignore_ascii_case(header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Method;
+    use origin::{OriginMatcher, OriginSettings};
+    use path::PathPolicy;
+
+    #[test]
+    fn test_default_is_compliant() {
+        assert_eq!(CORSMiddleware::default().validate_spec_compliance(), Ok(()));
+    }
+
+    #[test]
+    fn test_wildcard_origin() {
+        let middleware = CORSMiddleware::builder()
+            .origin(Some("*".to_string()))
+            .allow_credentials(true)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![SpecViolation::WildcardOriginWithCredentials])
+        );
+    }
+
+    #[test]
+    fn test_wildcards_without_credentials() {
+        let middleware = CORSMiddleware::builder()
+            .origin(Some("*".to_string()))
+            .allow_headers(vec!["*"])
+            .allow_credentials(false)
+            .build();
+
+        assert_eq!(middleware.validate_spec_compliance(), Ok(()));
+    }
+
+    #[test]
+    fn test_wildcard_headers() {
+        let middleware = CORSMiddleware::builder()
+            .actual_allow_headers(ActualAllowHeaders::List(vec!["*".to_string()]))
+            .allow_credentials(true)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![SpecViolation::WildcardHeadersWithCredentials])
+        );
+    }
+
+    #[test]
+    fn test_wildcard_methods() {
+        let middleware = CORSMiddleware::builder()
+            .path_policies(vec![PathPolicy::new(
+                "/users/{id}",
+                vec![Method::Extension("*".to_string())],
+                vec![],
+            )])
+            .allow_credentials(true)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![SpecViolation::WildcardMethodsWithCredentials])
+        );
+    }
+
+    #[test]
+    fn test_forbidden_headers() {
+        let middleware = CORSMiddleware::builder()
+            .origin(Some("*".to_string()))
+            .allow_headers(vec!["Content-Type", "cookie", "Sec-Fetch-Mode", "Cookie"])
+            .allow_credentials(true)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![
+                SpecViolation::WildcardOriginWithCredentials,
+                SpecViolation::ForbiddenHeader("cookie".to_string()),
+                SpecViolation::ForbiddenHeader("Sec-Fetch-Mode".to_string()),
+                SpecViolation::ForbiddenHeader("Cookie".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_wildcard_expose() {
+        let middleware = CORSMiddleware::builder()
+            .expose_headers(vec!["*"])
+            .allow_credentials(true)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![SpecViolation::WildcardExposeWithCredentials])
+        );
+    }
+
+    #[test]
+    fn test_forbidden_exposed_headers() {
+        let middleware = CORSMiddleware::builder()
+            .expose_headers(vec!["X-Total-Count", "set-cookie", "Set-Cookie2"])
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![
+                SpecViolation::ForbiddenExposedHeader("set-cookie".to_string()),
+                SpecViolation::ForbiddenExposedHeader("Set-Cookie2".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_per_origin_credentials() {
+        let matcher: OriginMatcher = "https://app.example.com".parse().unwrap();
+
+        let middleware = CORSMiddleware::builder()
+            .allowed_matchers(vec![matcher.with_settings(OriginSettings {
+                credentials: Some(true),
+                max_age: None,
+            })])
+            .allow_headers(vec!["*"])
+            .allow_credentials(false)
+            .build();
+
+        assert_eq!(
+            middleware.validate_spec_compliance(),
+            Err(vec![SpecViolation::WildcardHeadersWithCredentials])
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Matching of request origins against large lists of domain
+//! suffixes, compiled into a single Aho-Corasick automaton.
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use std::fmt;
+
+use origin::SerializedOrigin;
+
+/// A list of domain suffixes, such as "example.com", each of
+/// which allows the domain itself and every subdomain below it.
+///
+/// All suffixes are searched for in one pass over the host, so
+/// the cost of a lookup barely grows with the size of the list.
+#[derive(Clone)]
+pub(crate) struct SuffixAllowlist {
+    suffixes: Vec<String>,
+    automaton: AhoCorasick,
+}
+
+impl SuffixAllowlist {
+    pub(crate) fn new<I, S>(suffixes: I) -> SuffixAllowlist
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let suffixes: Vec<String> = suffixes
+            .into_iter()
+            .map(Into::into)
+            .map(|suffix| {
+                suffix
+                    .trim_start_matches('*')
+                    .trim_start_matches('.')
+                    .to_ascii_lowercase()
+            })
+            .filter(|suffix| !suffix.is_empty())
+            .collect();
+
+        let automaton = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(true)
+            .build(&suffixes);
+
+        SuffixAllowlist {
+            suffixes,
+            automaton,
+        }
+    }
+
+    pub(crate) fn suffixes(&self) -> &[String] {
+        &self.suffixes
+    }
+
+    /// Returns true when the host of the origin is one of the
+    /// suffixes or a subdomain of one, whatever its port.
+    pub(crate) fn matches(&self, origin: &SerializedOrigin) -> bool {
+        let host = origin.host();
+
+        self.automaton.find_overlapping_iter(host).any(|found| {
+            found.end() == host.len()
+                && (found.start() == 0 || host.as_bytes()[found.start() - 1] == b'.')
+        })
+    }
+}
+
+impl fmt::Debug for SuffixAllowlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SuffixAllowlist")
+            .field("suffixes", &self.suffixes.len())
+            .finish()
+    }
+}
+
+impl PartialEq for SuffixAllowlist {
+    fn eq(&self, other: &SuffixAllowlist) -> bool {
+        self.suffixes == other.suffixes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(allowlist: &SuffixAllowlist, origin: &str) -> bool {
+        allowlist.matches(&SerializedOrigin::parse(origin).unwrap())
+    }
+
+    #[test]
+    fn test_large_suffix_allowlist() {
+        let allowlist =
+            SuffixAllowlist::new((0..50000).map(|i| format!("tenant{}.example.com", i)));
+
+        assert!(matches(&allowlist, "https://tenant0.example.com"));
+        assert!(matches(&allowlist, "https://tenant49999.example.com:8443"));
+        assert!(matches(&allowlist, "https://app.TENANT123.example.com"));
+
+        // Near misses sharing most of a suffix
+        assert!(!matches(&allowlist, "https://tenant50000.example.com"));
+        assert!(!matches(&allowlist, "https://eviltenant1.example.com"));
+        assert!(!matches(&allowlist, "https://tenant1.example.com.evil.net"));
+        assert!(!matches(&allowlist, "https://example.com"));
+    }
+
+    #[test]
+    fn test_suffix_patterns_normalised() {
+        let allowlist = SuffixAllowlist::new(vec!["*.Example.com", ".example.org", ""]);
+
+        assert_eq!(allowlist.suffixes(), &["example.com", "example.org"]);
+        assert!(matches(&allowlist, "http://example.com"));
+        assert!(matches(&allowlist, "http://www.example.org"));
+        assert!(!matches(&allowlist, "http://notexample.org"));
+    }
+}
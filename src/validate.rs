@@ -0,0 +1,212 @@
+//! Checks that the configured methods, header names and origin
+//! can be sent in a header, so that no configuration produces a
+//! malformed response.
+use error::CorsError;
+use headers::ActualAllowHeaders;
+use methods::AllowedMethods;
+use path::PathPolicy;
+use CORSMiddleware;
+
+/// Returns true for a token, the form methods and header names
+/// take. Surrounding whitespace is ignored, as it is trimmed
+/// when the header lists are canonicalized.
+pub(crate) fn is_token(value: &str) -> bool {
+    let value = value.trim();
+
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Returns true for text which can be sent as a header value,
+/// having no line breaks or other control characters.
+pub(crate) fn is_header_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|byte| byte == b'\t' || (byte >= 0x20 && byte != 0x7f))
+}
+
+/// Keeps the values which are tokens, logging the others.
+fn retain_tokens<T: AsRef<str>>(values: &mut Vec<T>, header: &str) -> bool {
+    let before = values.len();
+
+    values.retain(|value| {
+        let valid = is_token(value.as_ref());
+
+        if !valid {
+            warn!(
+                "Leaving {:?} out of {}, it is not a valid token",
+                value.as_ref(),
+                header
+            );
+        }
+
+        valid
+    });
+
+    values.len() < before
+}
+
+/// Returns the first of the values which is not a token.
+fn first_invalid<T: AsRef<str>>(values: &[T]) -> Option<&str> {
+    values
+        .iter()
+        .map(AsRef::as_ref)
+        .find(|value| !is_token(value))
+}
+
+impl CORSMiddleware {
+    /// Finds the first configured value which cannot be sent in
+    /// the header it is meant for.
+    pub(crate) fn check_header_values(&self) -> Result<(), CorsError> {
+        let invalid = |header: &'static str, value: &str| CorsError::InvalidHeaderValue {
+            header,
+            value: value.to_string(),
+        };
+
+        if let Some(ref origin) = self.origin {
+            if !is_header_value(origin) {
+                return Err(invalid("Access-Control-Allow-Origin", origin));
+            }
+        }
+
+        let actual_allow_headers = match self.actual_allow_headers {
+            ActualAllowHeaders::List(ref list) => &list[..],
+            _ => &[],
+        };
+
+        let lists = vec![
+            ("Access-Control-Allow-Headers", &self.allow_headers[..]),
+            ("Access-Control-Allow-Headers", actual_allow_headers),
+            ("Access-Control-Expose-Headers", &self.expose_headers[..]),
+            ("Vary", &self.extra_vary[..]),
+        ];
+
+        for (header, values) in lists {
+            if let Some(value) = first_invalid(values) {
+                return Err(invalid(header, value));
+            }
+        }
+
+        let policies = self.path_policies.iter().map(PathPolicy::methods);
+
+        for methods in Some(self.methods.as_slice()).into_iter().chain(policies) {
+            if let Some(method) = first_invalid(methods) {
+                return Err(invalid("Access-Control-Allow-Methods", method));
+            }
+        }
+
+        for policy in &self.path_policies {
+            if let Some(header) = first_invalid(policy.headers()) {
+                return Err(invalid("Access-Control-Allow-Headers", header));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the configured methods and header names which are
+    /// not tokens, logging each one, so that dropping them only
+    /// ever narrows what is allowed. A fixed origin which cannot
+    /// be sent is kept, and left off responses instead.
+    pub(crate) fn drop_invalid_header_values(&mut self) {
+        let mut methods = self.methods.to_vec();
+
+        if retain_tokens(&mut methods, "Access-Control-Allow-Methods") {
+            self.methods = AllowedMethods::new(methods);
+        }
+
+        retain_tokens(&mut self.allow_headers, "Access-Control-Allow-Headers");
+        retain_tokens(&mut self.expose_headers, "Access-Control-Expose-Headers");
+        retain_tokens(&mut self.extra_vary, "Vary");
+
+        if let ActualAllowHeaders::List(ref mut list) = self.actual_allow_headers {
+            retain_tokens(list, "Access-Control-Allow-Headers");
+        }
+
+        for policy in &mut self.path_policies {
+            let mut methods = policy.methods().to_vec();
+            let mut headers = policy.headers().to_vec();
+
+            let dropped = retain_tokens(&mut methods, "Access-Control-Allow-Methods")
+                | retain_tokens(&mut headers, "Access-Control-Allow-Headers");
+
+            if dropped {
+                *policy = PathPolicy::new(policy.template(), methods, headers);
+            }
+        }
+
+        if let Some(ref origin) = self.origin {
+            if !is_header_value(origin) {
+                warn!(
+                    "The fixed origin {:?} cannot be sent, it will be left off",
+                    origin
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Method;
+
+    #[test]
+    fn test_tokens() {
+        assert!(is_token("X-Api-Key"));
+        assert!(is_token(" x-api-key "));
+        assert!(is_token("*"));
+        assert!(!is_token(""));
+        assert!(!is_token("X Api Key"));
+        assert!(!is_token("X-Bad\r\nSet-Cookie: id=1"));
+        assert!(!is_token("X-Caf\u{e9}"));
+    }
+
+    #[test]
+    fn test_header_values() {
+        assert!(is_header_value("https://app.example.com"));
+        assert!(is_header_value("a, b\tc"));
+        assert!(!is_header_value(
+            "https://app.example.com\r\nSet-Cookie: id=1"
+        ));
+        assert!(!is_header_value("\0"));
+    }
+
+    #[test]
+    fn test_check_header_values() {
+        assert_eq!(CORSMiddleware::default().check_header_values(), Ok(()));
+
+        let middleware = CORSMiddleware::builder()
+            .methods(vec![Method::Get, Method::Extension("GET ME".to_string())])
+            .build();
+
+        // Built middleware have already dropped the bad values
+        assert_eq!(middleware.check_header_values(), Ok(()));
+        assert!(!middleware.allows_method(&Method::Extension("GET ME".to_string())));
+
+        let middleware = CORSMiddleware::new(
+            vec![Method::Extension("GET ME".to_string())],
+            Some("https://app.example.com\r\n".to_string()),
+            60,
+        );
+
+        assert_eq!(
+            CORSMiddleware {
+                origin: None,
+                ..middleware.clone()
+            }
+            .check_header_values(),
+            Ok(())
+        );
+        assert_eq!(
+            middleware.check_header_values(),
+            Err(CorsError::InvalidHeaderValue {
+                header: "Access-Control-Allow-Origin",
+                value: "https://app.example.com\r\n".to_string(),
+            })
+        );
+    }
+}